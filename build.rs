@@ -461,6 +461,8 @@ fn generate_bindings<P: AsRef<Path>>(include_path: &P) {
     .clang_arg(format!("-I{}", include_path.display()))
     .derive_debug(true)
     .derive_default(true)
+    // `Loop` implements `Drop`, which is not allowed on `Copy` types.
+    .no_copy("uv_loop_s")
     .prepend_enum_name(false)
     .whitelist_type("uv_.+")
     .whitelist_function("uv_.+")
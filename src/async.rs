@@ -0,0 +1,185 @@
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::context::{self, Slot};
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::handle::{Handle, HandleImpl};
+use crate::bindings::{
+  uv_async_t, uv_async_cb, uv_handle_t,
+  uv_async_init, uv_async_send
+};
+
+pub type AsyncCb = uv_async_cb;
+
+type AsyncFn = Box<dyn FnMut(&mut Async)>;
+
+repr!{
+  pub type Async = uv_async_t;
+}
+
+/// Raw handle pointer shared with the senders. Set to `None` when the handle is closed.
+struct AsyncPtr(*mut uv_async_t);
+
+unsafe impl Send for AsyncPtr {}
+unsafe impl Sync for AsyncPtr {}
+
+type Shared = Arc<RwLock<Option<AsyncPtr>>>;
+
+/// Kept in the handle context: invalidates the senders once the handle is closed.
+struct SenderGuard(Shared);
+
+impl Drop for SenderGuard {
+  fn drop(&mut self) {
+    let mut ptr = self.0.write().unwrap_or_else(|err| err.into_inner());
+    *ptr = None;
+  }
+}
+
+/// Thread-safe half of an `Async` handle, used to wake up its loop from other threads.
+///
+/// Once the handle has been closed, `send()` fails with `EBADF`.
+#[derive(Clone)]
+pub struct AsyncSender {
+  inner: Shared,
+}
+
+impl AsyncSender {
+  /// Wake up the event loop and call the async handle’s callback.
+  /// Calls made before the callback runs are coalesced into a single invocation.
+  pub fn send(&self) -> Result<(), Error> {
+    let ptr = self.inner.read().unwrap_or_else(|err| err.into_inner());
+
+    let rc = match ptr.as_ref() {
+      Some(ptr) => unsafe { uv_async_send(ptr.0) },
+      None => return Err(Error::EBADF)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+}
+
+impl fmt::Debug for AsyncSender {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let ptr = self.inner.read().unwrap_or_else(|err| err.into_inner());
+    fmt
+      .debug_struct("AsyncSender")
+      .field("handle", &ptr.as_ref().map(|ptr| ptr.0))
+      .finish()
+  }
+}
+
+unsafe extern "C" fn async_cb(handle: *mut uv_async_t) {
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut AsyncFn| {
+    cb(&mut *(handle as *mut Async))
+  });
+}
+
+impl Async {
+  /// Initialize the handle. A `None` callback is allowed.
+  /// Unlike other handle initialization functions, it immediately starts the handle.
+  pub fn init(&mut self, l: &Loop, cb: AsyncCb) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_async_init(l.as_raw(), self.as_mut_ptr(), cb)
+    };
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+    Ok(())
+  }
+
+  /// Same as `init()`, with a closure as callback.
+  pub fn init_with<F>(&mut self, l: &Loop, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Async) + 'static
+  {
+    self.init(l, Some(async_cb))?;
+    context::set::<AsyncFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
+  /// Wake up the event loop and call the async handle’s callback.
+  ///
+  /// libuv coalesces calls to `send()`: if it is called several times before the callback
+  /// had a chance to run, the callback is invoked only once.
+  pub fn send(&mut self) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_async_send(self.as_mut_ptr())
+    };
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+    Ok(())
+  }
+
+  /// Returns a `Send + Sync` sender which other threads can use to wake up this handle.
+  pub fn sender(&mut self) -> AsyncSender {
+    let handle = self.as_mut_handle().as_mut_ptr();
+
+    if let Some(guard) = unsafe { context::get::<SenderGuard>(handle, Slot::Extra) } {
+      return AsyncSender { inner: guard.0.clone() };
+    }
+
+    let inner: Shared = Arc::new(RwLock::new(Some(AsyncPtr(self.as_mut_ptr()))));
+    context::set(self.as_mut_handle(), Slot::Extra, SenderGuard(inner.clone()));
+
+    AsyncSender { inner }
+  }
+}
+
+impl HandleImpl for Async {
+  fn as_handle(&self) -> &Handle {
+    unsafe {
+      let ptr = self.as_ptr() as *const Handle;
+      ptr.as_ref().unwrap()
+    }
+  }
+  fn as_mut_handle(&mut self) -> &mut Handle {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Handle;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+impl fmt::Debug for Async {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("Async")
+      .field("data",  &self.data)
+      .field("loop",  &self.loop_)
+      .field("type",  &self.type_)
+      .field("close_cb",  &self.close_cb)
+      .field("handle_queue",  &self.handle_queue)
+      .field("next_closing",  &self.next_closing)
+      .field("flags",  &self.flags)
+      .field("async_cb",  &self.async_cb)
+      .field("queue",  &self.queue)
+      .field("pending",  &self.pending)
+      .finish()
+  }
+}
+
+#[test]
+fn test_layout_async() {
+  use std::mem;
+  assert_eq!(
+    mem::size_of::<Async>(),
+    mem::size_of::<uv_async_t>(),
+    concat!("Size of: ", stringify!(Async))
+  );
+  assert_eq!(
+    mem::align_of::<Async>(),
+    mem::align_of::<uv_async_t>(),
+    concat!("Alignment of ", stringify!(Async))
+  );
+  assert_eq!(
+    mem::align_of::<Async>(),
+    mem::align_of::<Handle>(),
+    concat!("Alignment of ", stringify!(Async))
+  );
+}
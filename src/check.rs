@@ -1,23 +1,32 @@
 use std::fmt;
 
+use crate::context::{self, Slot};
 use crate::r#loop::Loop;
 use crate::error::Error;
 use crate::handle::{Handle, HandleImpl};
 use crate::bindings::{
-  uv_check_t, uv_check_cb,
+  uv_check_t, uv_check_cb, uv_handle_t,
   uv_check_init, uv_check_start, uv_check_stop
 };
 
 pub type CheckCb = uv_check_cb;
 
+type CheckFn = Box<dyn FnMut(&mut Check)>;
+
 repr!{
   pub type Check = uv_check_t;
 }
 
+unsafe extern "C" fn check_cb(handle: *mut uv_check_t) {
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut CheckFn| {
+    cb(&mut *(handle as *mut Check))
+  });
+}
+
 impl Check {
-  pub fn init(&mut self, l: &mut Loop) -> Result<(), Error> {
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
     let rc = unsafe {
-      uv_check_init(l.as_mut_ptr(), self.as_mut_ptr())
+      uv_check_init(l.as_raw(), self.as_mut_ptr())
     };
     if rc < 0 {
       return Err(Error::from(rc));
@@ -35,6 +44,16 @@ impl Check {
     Ok(())
   }
 
+  /// Same as `start()`, with a closure as callback.
+  pub fn start_with<F>(&mut self, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Check) + 'static
+  {
+    self.start(Some(check_cb))?;
+    context::set::<CheckFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
   pub fn stop(&mut self) -> Result<(), Error> {
     let rc = unsafe {
       uv_check_stop(self.as_mut_ptr())
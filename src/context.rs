@@ -0,0 +1,158 @@
+//! Rust closures attached to a handle.
+//!
+//! The closures live in a `HandleContext` stored in the handle `data` slot. Handles owning
+//! a context are tracked so that `HandleImpl::close` can route the close through
+//! `close_trampoline`, which drops the closures once libuv is done with the handle.
+
+use std::any::Any;
+use std::sync::Mutex;
+use std::collections::BTreeMap;
+
+use crate::handle::{Handle, CloseCb};
+use crate::bindings::{
+  uv_handle_t, uv_handle_get_data, uv_handle_set_data
+};
+
+/// Callback slots available on every handle.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Slot {
+  /// The main callback of the handle (`async_cb`, `timer_cb`, `read_cb`, ...).
+  Callback    = 0,
+  /// Crate-internal state that must be released with the handle.
+  Extra       = 1,
+  /// Read buffer handed to libuv by the alloc callback of closure-based reads.
+  Alloc       = 2,
+  /// The `connection_cb` of a listening stream.
+  Connection  = 3,
+}
+
+const SLOTS: usize = 4;
+
+#[derive(Default)]
+pub(crate) struct HandleContext {
+  slots:    [Option<Box<dyn Any>>; SLOTS],
+  close_cb: CloseCb,
+  /// Releases a handle allocated by the crate, once it is closed.
+  free_cb:  CloseCb,
+}
+
+/// Handle address -> context address, for every handle whose `data` slot holds a `HandleContext`.
+static CONTEXTS: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+fn registry() -> std::sync::MutexGuard<'static, BTreeMap<usize, usize>> {
+  CONTEXTS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Returns the context owned by `handle`, if its `data` slot still points to it.
+fn lookup(handle: *const uv_handle_t) -> Option<*mut HandleContext> {
+  let data = unsafe { uv_handle_get_data(handle) } as usize;
+
+  match registry().get(&(handle as usize)) {
+    Some(&ctx) if ctx == data => Some(ctx as *mut HandleContext),
+    _ => None
+  }
+}
+
+/// Returns the context of `handle`, creating it (and taking over the `data` slot) if needed.
+pub(crate) fn context(handle: &mut Handle) -> &mut HandleContext {
+  let ptr = handle.as_mut_ptr();
+
+  let ctx = match lookup(ptr) {
+    Some(ctx) => ctx,
+    None => {
+      let ctx = Box::into_raw(Box::<HandleContext>::default());
+      unsafe { uv_handle_set_data(ptr, ctx as *mut _) };
+      registry().insert(ptr as usize, ctx as usize);
+      ctx
+    }
+  };
+
+  unsafe { &mut *ctx }
+}
+
+/// Store `value` in the given slot of the handle context.
+pub(crate) fn set<T: 'static>(handle: &mut Handle, slot: Slot, value: T) {
+  context(handle).slots[slot as usize] = Some(Box::new(value));
+}
+
+/// Access the value stored in the given slot, if the handle owns a context.
+///
+/// # Safety
+/// `handle` must point to a live handle.
+pub(crate) unsafe fn get<'a, T: 'static>(handle: *const uv_handle_t, slot: Slot) -> Option<&'a mut T> {
+  let ctx = lookup(handle)?;
+  (*ctx).slots[slot as usize].as_mut()?.downcast_mut::<T>()
+}
+
+/// Invoke the closure stored in `slot`.
+///
+/// The closure is moved out of the context for the duration of the call, so it can safely
+/// replace itself (e.g. by restarting the handle with a new closure) or close its handle.
+///
+/// # Safety
+/// `handle` must point to a live handle.
+pub(crate) unsafe fn invoke<T: 'static, R>(
+  handle: *mut uv_handle_t,
+  slot: Slot,
+  call: impl FnOnce(&mut T) -> R
+) -> Option<R> {
+  let ctx = lookup(handle)?;
+  let mut value = (*ctx).slots[slot as usize].take()?;
+  let ret = value.downcast_mut::<T>().map(call);
+
+  if (*ctx).slots[slot as usize].is_none() {
+    (*ctx).slots[slot as usize] = Some(value);
+  }
+
+  ret
+}
+
+/// Returns `true` when a context was attached to the handle.
+pub(crate) fn is_owned(handle: *const uv_handle_t) -> bool {
+  registry().contains_key(&(handle as usize))
+}
+
+/// Remember the user `close_cb` so `close_trampoline` can chain to it.
+///
+/// # Safety
+/// `handle` must point to a live handle.
+pub(crate) unsafe fn set_close_cb(handle: *const uv_handle_t, close_cb: CloseCb) {
+  if let Some(&ctx) = registry().get(&(handle as usize)) {
+    (*(ctx as *mut HandleContext)).close_cb = close_cb;
+  }
+}
+
+/// Set the callback releasing a handle allocated by the crate. It is called by
+/// `close_trampoline` after the user `close_cb`, however the handle gets closed.
+///
+/// # Safety
+/// `handle` must point to a live handle.
+pub(crate) unsafe fn set_free_cb(handle: *mut uv_handle_t, free_cb: CloseCb) {
+  context(&mut *(handle as *mut Handle)).free_cb = free_cb;
+}
+
+/// Close callback installed by `HandleImpl::close` for handles owning a context.
+/// Drops the closures, restores a null `data` slot, then chains to the user `close_cb` and
+/// to the `free_cb` of the handle.
+pub(crate) unsafe extern "C" fn close_trampoline(handle: *mut uv_handle_t) {
+  let ctx = match registry().remove(&(handle as usize)) {
+    Some(ctx) => ctx as *mut HandleContext,
+    None => return
+  };
+
+  if std::ptr::eq(uv_handle_get_data(handle), ctx as *mut _) {
+    uv_handle_set_data(handle, std::ptr::null_mut());
+  }
+
+  let ctx = Box::from_raw(ctx);
+  let (close_cb, free_cb) = (ctx.close_cb, ctx.free_cb);
+  drop(ctx);
+
+  if let Some(close_cb) = close_cb {
+    close_cb(handle);
+  }
+
+  if let Some(free_cb) = free_cb {
+    free_cb(handle);
+  }
+}
@@ -0,0 +1,317 @@
+//! Asynchronous and synchronous `getaddrinfo(3)` / `getnameinfo(3)`.
+
+use std::ptr;
+use std::ffi::{CStr, CString};
+use std::net::SocketAddr;
+use std::os::raw::{c_char, c_int, c_void};
+use bitflags::bitflags;
+
+use crate::net;
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::req::{GetAddrInfo, GetNameInfo};
+use crate::bindings::{
+  addrinfo, sockaddr, uv_getaddrinfo_t, uv_getnameinfo_t,
+  uv_getaddrinfo, uv_freeaddrinfo, uv_getnameinfo,
+  AF_UNSPEC, AF_INET, AF_INET6, SOCK_STREAM, SOCK_DGRAM, SOCK_RAW,
+  IPPROTO_IP, IPPROTO_TCP, IPPROTO_UDP,
+  AI_PASSIVE, AI_CANONNAME, AI_NUMERICHOST, AI_NUMERICSERV,
+  AI_V4MAPPED, AI_ALL, AI_ADDRCONFIG,
+  NI_NUMERICHOST, NI_NUMERICSERV, NI_NOFQDN, NI_NAMEREQD, NI_DGRAM
+};
+
+#[repr(i32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Family {
+  #[default]
+  Unspec  = AF_UNSPEC as i32,
+  Inet    = AF_INET as i32,
+  Inet6   = AF_INET6 as i32,
+}
+
+#[repr(i32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SockType {
+  #[default]
+  Any     = 0,
+  Stream  = SOCK_STREAM as i32,
+  Dgram   = SOCK_DGRAM as i32,
+  Raw     = SOCK_RAW as i32,
+}
+
+#[repr(i32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Protocol {
+  #[default]
+  Any     = IPPROTO_IP as i32,
+  Tcp     = IPPROTO_TCP as i32,
+  Udp     = IPPROTO_UDP as i32,
+}
+
+bitflags! {
+  /// `AI_*` flags of the `getaddrinfo()` hints.
+  #[derive(Default)]
+  pub struct AddrInfoFlags: c_int {
+    /// The returned addresses are suitable for binding a socket that accepts connections.
+    const PASSIVE     = AI_PASSIVE as c_int;
+    /// Return the canonical name of the host.
+    const CANONNAME   = AI_CANONNAME as c_int;
+    /// The node must be a numerical address, no name resolution is performed.
+    const NUMERICHOST = AI_NUMERICHOST as c_int;
+    /// The service must be a numerical port.
+    const NUMERICSERV = AI_NUMERICSERV as c_int;
+    /// Return IPv4-mapped IPv6 addresses when no IPv6 address is found.
+    const V4MAPPED    = AI_V4MAPPED as c_int;
+    /// With `V4MAPPED`, return both IPv6 and IPv4-mapped IPv6 addresses.
+    const ALL         = AI_ALL as c_int;
+    /// Only return address families configured on the local system.
+    const ADDRCONFIG  = AI_ADDRCONFIG as c_int;
+  }
+}
+
+bitflags! {
+  /// `NI_*` flags of `getnameinfo()`.
+  #[derive(Default)]
+  pub struct NameInfoFlags: c_int {
+    /// Return the numeric form of the host address.
+    const NUMERICHOST = NI_NUMERICHOST as c_int;
+    /// Return the numeric form of the service (port number).
+    const NUMERICSERV = NI_NUMERICSERV as c_int;
+    /// Return only the hostname part of the FQDN for local hosts.
+    const NOFQDN      = NI_NOFQDN as c_int;
+    /// Fail with `EAI_NONAME` if the hostname cannot be determined.
+    const NAMEREQD    = NI_NAMEREQD as c_int;
+    /// The service is datagram (UDP) based rather than stream (TCP) based.
+    const DGRAM       = NI_DGRAM as c_int;
+  }
+}
+
+/// Hints restricting the addresses returned by `getaddrinfo()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct AddrInfoHints {
+  pub family:   Family,
+  pub socktype: SockType,
+  pub protocol: Protocol,
+  pub flags:    AddrInfoFlags,
+}
+
+impl From<&AddrInfoHints> for addrinfo {
+  fn from(hints: &AddrInfoHints) -> Self {
+    addrinfo {
+      ai_flags:     hints.flags.bits(),
+      ai_family:    hints.family as c_int,
+      ai_socktype:  hints.socktype as c_int,
+      ai_protocol:  hints.protocol as c_int,
+      ..addrinfo::default()
+    }
+  }
+}
+
+/// Result of `getaddrinfo()`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AddrInfo {
+  /// Canonical name of the host, when `AddrInfoFlags::CANONNAME` was requested.
+  pub canonical_name: Option<String>,
+  /// Resolved addresses, in the order returned by the resolver.
+  /// Duplicates (one entry per socket type) are merged.
+  pub addrs: Vec<SocketAddr>,
+}
+
+impl AddrInfo {
+  /// # Safety
+  /// `res` must be null or point to a valid `addrinfo` list.
+  unsafe fn from_raw(res: *const addrinfo) -> Self {
+    let mut info = AddrInfo::default();
+    let mut ai = res;
+
+    while let Some(entry) = ai.as_ref() {
+      if info.canonical_name.is_none() && !entry.ai_canonname.is_null() {
+        info.canonical_name = Some(CStr::from_ptr(entry.ai_canonname).to_string_lossy().into_owned());
+      }
+
+      if let Some(addr) = net::from_sockaddr(entry.ai_addr) {
+        if !info.addrs.contains(&addr) {
+          info.addrs.push(addr);
+        }
+      }
+
+      ai = entry.ai_next;
+    }
+
+    info
+  }
+}
+
+type AddrInfoFn = Box<dyn FnOnce(Result<AddrInfo, Error>)>;
+type NameInfoFn = Box<dyn FnOnce(Result<(String, String), Error>)>;
+
+fn to_cstring(s: Option<&str>) -> Result<Option<CString>, Error> {
+  s.map(|s| CString::new(s).map_err(|_| Error::EINVAL)).transpose()
+}
+
+fn as_ptr(s: &Option<CString>) -> *const c_char {
+  s.as_ref().map_or(ptr::null(), |s| s.as_ptr())
+}
+
+unsafe fn to_string(s: *const c_char) -> String {
+  CStr::from_ptr(s).to_string_lossy().into_owned()
+}
+
+unsafe extern "C" fn getaddrinfo_cb(req: *mut uv_getaddrinfo_t, status: c_int, res: *mut addrinfo) {
+  let req = Box::from_raw(req as *mut GetAddrInfo);
+  let cb = Box::from_raw(req.data as *mut AddrInfoFn);
+
+  let ret = if status < 0 {
+    Err(Error::from(status))
+  } else {
+    Ok(AddrInfo::from_raw(res))
+  };
+
+  uv_freeaddrinfo(res);
+  cb(ret)
+}
+
+unsafe extern "C" fn getnameinfo_cb(req: *mut uv_getnameinfo_t, status: c_int, hostname: *const c_char, service: *const c_char) {
+  let req = Box::from_raw(req as *mut GetNameInfo);
+  let cb = Box::from_raw(req.data as *mut NameInfoFn);
+
+  let ret = if status < 0 {
+    Err(Error::from(status))
+  } else {
+    Ok((to_string(hostname), to_string(service)))
+  };
+
+  cb(ret)
+}
+
+/// Resolve `node` and / or `service` synchronously. At least one of them must be given.
+pub fn getaddrinfo(
+  l: &Loop,
+  node: Option<&str>,
+  service: Option<&str>,
+  hints: Option<&AddrInfoHints>
+) -> Result<AddrInfo, Error> {
+  let node = to_cstring(node)?;
+  let service = to_cstring(service)?;
+  let hints = hints.map(addrinfo::from);
+  let hints_ptr = hints.as_ref().map_or(ptr::null(), |hints| hints as *const addrinfo);
+
+  let mut req = GetAddrInfo::default();
+
+  let rc = unsafe {
+    uv_getaddrinfo(l.as_raw(), req.as_mut_ptr(), None, as_ptr(&node), as_ptr(&service), hints_ptr)
+  };
+
+  if rc < 0 {
+    return Err(Error::from(rc));
+  }
+
+  unsafe {
+    let info = AddrInfo::from_raw(req.addrinfo);
+    uv_freeaddrinfo(req.addrinfo);
+    Ok(info)
+  }
+}
+
+/// Asynchronous `getaddrinfo()`, resolved on the threadpool.
+/// Cancelling the request reports `EAI_CANCELED` to the callback.
+pub fn getaddrinfo_async<F>(
+  l: &Loop,
+  node: Option<&str>,
+  service: Option<&str>,
+  hints: Option<&AddrInfoHints>,
+  cb: F
+) -> Result<(), Error>
+where
+  F: FnOnce(Result<AddrInfo, Error>) + 'static
+{
+  let node = to_cstring(node)?;
+  let service = to_cstring(service)?;
+  let hints = hints.map(addrinfo::from);
+  let hints_ptr = hints.as_ref().map_or(ptr::null(), |hints| hints as *const addrinfo);
+
+  let cb: AddrInfoFn = Box::new(cb);
+  let req = Box::into_raw(GetAddrInfo::boxed());
+
+  unsafe {
+    (*req).data = Box::into_raw(Box::new(cb)) as *mut c_void;
+  }
+
+  let rc = unsafe {
+    uv_getaddrinfo(l.as_raw(), req, Some(getaddrinfo_cb), as_ptr(&node), as_ptr(&service), hints_ptr)
+  };
+
+  if rc < 0 {
+    unsafe {
+      drop(Box::from_raw((*req).data as *mut AddrInfoFn));
+      drop(Box::from_raw(req));
+    }
+    return Err(Error::from(rc));
+  }
+
+  Ok(())
+}
+
+/// Resolve `addr` into a `(host, service)` pair synchronously.
+pub fn getnameinfo(l: &Loop, addr: &SocketAddr, flags: NameInfoFlags) -> Result<(String, String), Error> {
+  let (storage, _) = net::to_sockaddr(addr);
+  let mut req = GetNameInfo::default();
+
+  let rc = unsafe {
+    uv_getnameinfo(l.as_raw(), req.as_mut_ptr(), None, &storage as *const _ as *const sockaddr, flags.bits())
+  };
+
+  if rc < 0 {
+    return Err(Error::from(rc));
+  }
+
+  unsafe {
+    Ok((to_string(req.host.as_ptr()), to_string(req.service.as_ptr())))
+  }
+}
+
+/// Asynchronous `getnameinfo()`, resolved on the threadpool.
+pub fn getnameinfo_async<F>(l: &Loop, addr: &SocketAddr, flags: NameInfoFlags, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<(String, String), Error>) + 'static
+{
+  let (storage, _) = net::to_sockaddr(addr);
+
+  let cb: NameInfoFn = Box::new(cb);
+  let req = Box::into_raw(GetNameInfo::boxed());
+
+  unsafe {
+    (*req).data = Box::into_raw(Box::new(cb)) as *mut c_void;
+  }
+
+  let rc = unsafe {
+    uv_getnameinfo(l.as_raw(), req, Some(getnameinfo_cb), &storage as *const _ as *const sockaddr, flags.bits())
+  };
+
+  if rc < 0 {
+    unsafe {
+      drop(Box::from_raw((*req).data as *mut NameInfoFn));
+      drop(Box::from_raw(req));
+    }
+    return Err(Error::from(rc));
+  }
+
+  Ok(())
+}
+
+#[test]
+fn test_addrinfo_hints() {
+  let hints = AddrInfoHints {
+    family:   Family::Inet6,
+    socktype: SockType::Stream,
+    protocol: Protocol::Tcp,
+    flags:    AddrInfoFlags::PASSIVE | AddrInfoFlags::NUMERICHOST,
+  };
+
+  let ai = addrinfo::from(&hints);
+  assert_eq!(ai.ai_family, libc::AF_INET6);
+  assert_eq!(ai.ai_socktype, libc::SOCK_STREAM);
+  assert_eq!(ai.ai_protocol, libc::IPPROTO_TCP);
+  assert_eq!(ai.ai_flags, libc::AI_PASSIVE | libc::AI_NUMERICHOST);
+  assert!(ai.ai_addr.is_null());
+}
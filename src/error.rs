@@ -2,6 +2,7 @@ pub use crate::bindings::*;
 pub use crate::thiserror::Error as ThisError;
 
 #[repr(i32)]
+#[allow(non_camel_case_types)]
 #[derive(ThisError, Debug, Clone, Copy, PartialEq)]
 pub enum Error {
   #[error("Operation not permitted (UV_EPERM)")]
@@ -210,6 +211,36 @@ pub enum Error {
   EOWNERDEAD,
   #[error("Interface output queue is full (UV_EQFULL)")]
   EQFULL,
+  #[error("Address family not supported (UV_EAI_ADDRFAMILY)")]
+  EAI_ADDRFAMILY,
+  #[error("Temporary failure (UV_EAI_AGAIN)")]
+  EAI_AGAIN,
+  #[error("Bad ai_flags value (UV_EAI_BADFLAGS)")]
+  EAI_BADFLAGS,
+  #[error("Invalid value for hints (UV_EAI_BADHINTS)")]
+  EAI_BADHINTS,
+  #[error("Request canceled (UV_EAI_CANCELED)")]
+  EAI_CANCELED,
+  #[error("Permanent failure (UV_EAI_FAIL)")]
+  EAI_FAIL,
+  #[error("Unsupported ai_family (UV_EAI_FAMILY)")]
+  EAI_FAMILY,
+  #[error("Out of memory (UV_EAI_MEMORY)")]
+  EAI_MEMORY,
+  #[error("No address (UV_EAI_NODATA)")]
+  EAI_NODATA,
+  #[error("Unknown node or service (UV_EAI_NONAME)")]
+  EAI_NONAME,
+  #[error("Argument buffer overflow (UV_EAI_OVERFLOW)")]
+  EAI_OVERFLOW,
+  #[error("Resolved protocol is unknown (UV_EAI_PROTOCOL)")]
+  EAI_PROTOCOL,
+  #[error("Service not available for socket type (UV_EAI_SERVICE)")]
+  EAI_SERVICE,
+  #[error("Socket type not supported (UV_EAI_SOCKTYPE)")]
+  EAI_SOCKTYPE,
+  #[error("End of file (UV_EOF)")]
+  EOF,
   #[error("Must be equal largest errno (UV_MAX)")]
   MAX,
 }
@@ -281,6 +312,21 @@ impl From<Error> for i32 {
       ECANCELED => UV_ECANCELED,
       EILSEQ => UV_EILSEQ,
       EPROTO => UV_EPROTO,
+      EAI_ADDRFAMILY => UV_EAI_ADDRFAMILY,
+      EAI_AGAIN => UV_EAI_AGAIN,
+      EAI_BADFLAGS => UV_EAI_BADFLAGS,
+      EAI_BADHINTS => UV_EAI_BADHINTS,
+      EAI_CANCELED => UV_EAI_CANCELED,
+      EAI_FAIL => UV_EAI_FAIL,
+      EAI_FAMILY => UV_EAI_FAMILY,
+      EAI_MEMORY => UV_EAI_MEMORY,
+      EAI_NODATA => UV_EAI_NODATA,
+      EAI_NONAME => UV_EAI_NONAME,
+      EAI_OVERFLOW => UV_EAI_OVERFLOW,
+      EAI_PROTOCOL => UV_EAI_PROTOCOL,
+      EAI_SERVICE => UV_EAI_SERVICE,
+      EAI_SOCKTYPE => UV_EAI_SOCKTYPE,
+      EOF => UV_EOF,
       MAX => UV_ERRNO_MAX,
       _ => unimplemented!()
     } 
@@ -356,6 +402,21 @@ impl From<i32> for Error {
       UV_ECANCELED       => Error::ECANCELED,
       UV_EILSEQ          => Error::EILSEQ,
       UV_EPROTO          => Error::EPROTO,
+      UV_EAI_ADDRFAMILY  => Error::EAI_ADDRFAMILY,
+      UV_EAI_AGAIN       => Error::EAI_AGAIN,
+      UV_EAI_BADFLAGS    => Error::EAI_BADFLAGS,
+      UV_EAI_BADHINTS    => Error::EAI_BADHINTS,
+      UV_EAI_CANCELED    => Error::EAI_CANCELED,
+      UV_EAI_FAIL        => Error::EAI_FAIL,
+      UV_EAI_FAMILY      => Error::EAI_FAMILY,
+      UV_EAI_MEMORY      => Error::EAI_MEMORY,
+      UV_EAI_NODATA      => Error::EAI_NODATA,
+      UV_EAI_NONAME      => Error::EAI_NONAME,
+      UV_EAI_OVERFLOW    => Error::EAI_OVERFLOW,
+      UV_EAI_PROTOCOL    => Error::EAI_PROTOCOL,
+      UV_EAI_SERVICE     => Error::EAI_SERVICE,
+      UV_EAI_SOCKTYPE    => Error::EAI_SOCKTYPE,
+      UV_EOF             => Error::EOF,
       _                      => match err_code {
         UV_ERRNO_MAX => Error::MAX,
         _                 => unreachable!()
@@ -363,3 +424,32 @@ impl From<i32> for Error {
     }
   }
 }
+
+impl From<Error> for std::io::Error {
+  fn from(err: Error) -> Self {
+    use std::io::ErrorKind;
+    use Error::*;
+
+    let kind = match err {
+      EOF           => ErrorKind::UnexpectedEof,
+      ENOENT        => ErrorKind::NotFound,
+      EPERM         => ErrorKind::PermissionDenied,
+      EACCES        => ErrorKind::PermissionDenied,
+      ECONNREFUSED  => ErrorKind::ConnectionRefused,
+      ECONNRESET    => ErrorKind::ConnectionReset,
+      ECONNABORTED  => ErrorKind::ConnectionAborted,
+      ENOTCONN      => ErrorKind::NotConnected,
+      EADDRINUSE    => ErrorKind::AddrInUse,
+      EADDRNOTAVAIL => ErrorKind::AddrNotAvailable,
+      EPIPE         => ErrorKind::BrokenPipe,
+      EEXIST        => ErrorKind::AlreadyExists,
+      EAGAIN        => ErrorKind::WouldBlock,
+      EINVAL        => ErrorKind::InvalidInput,
+      ETIMEDOUT     => ErrorKind::TimedOut,
+      EINTR         => ErrorKind::Interrupted,
+      _             => ErrorKind::Other,
+    };
+
+    std::io::Error::new(kind, err)
+  }
+}
@@ -0,0 +1,424 @@
+//! Single-threaded executor running futures on a `Loop`.
+//!
+//! Spawned tasks are polled from the callback of an `Async` handle owned by the executor.
+//! Wakers signal that handle through `uv_async_send`, so tasks can be woken from any thread.
+//! The handle is closed as soon as no task is left, so it does not keep the loop alive.
+
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use std::pin::{self, Pin};
+use std::future::Future;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, Wake, Waker};
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::r#loop::{self, Loop, RunMode};
+use crate::context;
+use crate::error::Error;
+use crate::r#async::{Async, AsyncSender};
+use crate::handle::HandleImpl;
+use crate::bindings::uv_handle_t;
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+/// State shared with the wakers, which may be used from any thread.
+struct Shared {
+  /// Tasks woken since the executor last ran.
+  woken:  Mutex<Vec<usize>>,
+  sender: AsyncSender,
+}
+
+struct TaskWaker {
+  id:     usize,
+  shared: Arc<Shared>,
+}
+
+impl Wake for TaskWaker {
+  fn wake(self: Arc<Self>) {
+    self.wake_by_ref()
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.shared.woken.lock().unwrap_or_else(|err| err.into_inner()).push(self.id);
+    // Fails once the executor is gone, in which case there is nothing left to wake.
+    let _ = self.shared.sender.send();
+  }
+}
+
+/// Waker of the future passed to `Loop::block_on()`.
+struct MainWaker {
+  woken:  AtomicBool,
+  sender: AsyncSender,
+}
+
+impl Wake for MainWaker {
+  fn wake(self: Arc<Self>) {
+    self.wake_by_ref()
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.woken.store(true, Ordering::Release);
+    let _ = self.sender.send();
+  }
+}
+
+/// Executor of a loop, alive while it has tasks or a `block_on()` is running.
+struct Executor {
+  handle:   *mut Async,
+  shared:   Arc<Shared>,
+  tasks:    RefCell<BTreeMap<usize, Task>>,
+  next_id:  Cell<usize>,
+  blocking: Cell<bool>,
+}
+
+thread_local! {
+  /// Loop address -> executor of that loop.
+  static EXECUTORS: RefCell<BTreeMap<usize, Rc<Executor>>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Kept in the context of the executor handle: unregisters the executor (dropping the tasks
+/// left) once the handle is closed, either by the executor or when its loop is dropped.
+struct Registration {
+  key:    usize,
+  handle: *mut Async,
+}
+
+impl Drop for Registration {
+  fn drop(&mut self) {
+    let executor = EXECUTORS.with(|executors| {
+      let mut executors = executors.borrow_mut();
+      match executors.get(&self.key) {
+        Some(executor) if executor.handle == self.handle => executors.remove(&self.key),
+        _ => None
+      }
+    });
+
+    drop(executor)
+  }
+}
+
+unsafe extern "C" fn free_cb(handle: *mut uv_handle_t) {
+  drop(Box::from_raw(handle as *mut Async));
+}
+
+impl Executor {
+  /// Returns the executor of `l`, creating it if needed.
+  fn get(l: &Loop) -> Result<Rc<Executor>, Error> {
+    let key = l.as_ptr() as usize;
+
+    let executor = EXECUTORS.with(|executors| executors.borrow().get(&key).cloned());
+
+    match executor {
+      Some(executor) if !unsafe { (*executor.handle).is_closing() } => return Ok(executor),
+      _ => {}
+    }
+
+    let mut handle = Async::boxed();
+    let registration = Registration { key, handle: &mut *handle as *mut Async };
+
+    handle.init_with(l, move |handle: &mut Async| {
+      let _ = &registration;
+      Executor::run(handle.get_loop());
+    })?;
+
+    let handle = Box::into_raw(handle);
+    unsafe { context::set_free_cb(handle as *mut uv_handle_t, Some(free_cb)) };
+
+    let executor = Rc::new(Executor {
+      handle,
+      shared:   Arc::new(Shared {
+        woken:  Mutex::new(Vec::new()),
+        sender: unsafe { (*handle).sender() },
+      }),
+      tasks:    RefCell::new(BTreeMap::new()),
+      next_id:  Cell::new(0),
+      blocking: Cell::new(false),
+    });
+
+    EXECUTORS.with(|executors| executors.borrow_mut().insert(key, executor.clone()));
+    Ok(executor)
+  }
+
+  fn spawn(&self, task: Task) {
+    let id = self.next_id.get();
+    self.next_id.set(id + 1);
+    self.tasks.borrow_mut().insert(id, task);
+
+    Arc::new(TaskWaker { id, shared: self.shared.clone() }).wake();
+  }
+
+  /// Poll the tasks woken since the last run.
+  fn run(l: &Loop) {
+    let key = l.as_ptr() as usize;
+
+    let executor = match EXECUTORS.with(|executors| executors.borrow().get(&key).cloned()) {
+      Some(executor) => executor,
+      None => return
+    };
+
+    let mut woken = mem::take(&mut *executor.shared.woken.lock().unwrap_or_else(|err| err.into_inner()));
+    woken.sort_unstable();
+    woken.dedup();
+
+    for id in woken {
+      // The task is taken out while it is polled, so that it can spawn other tasks.
+      let mut task = match executor.tasks.borrow_mut().remove(&id) {
+        Some(task) => task,
+        None => continue
+      };
+
+      let waker = Waker::from(Arc::new(TaskWaker { id, shared: executor.shared.clone() }));
+      let mut cx = Context::from_waker(&waker);
+
+      match panic::catch_unwind(AssertUnwindSafe(|| task.as_mut().poll(&mut cx))) {
+        Ok(Poll::Pending) => {
+          executor.tasks.borrow_mut().insert(id, task);
+        },
+        Ok(Poll::Ready(())) => {},
+        Err(payload) => r#loop::set_panic(l.as_raw(), payload),
+      }
+    }
+
+    executor.close_if_idle();
+  }
+
+  /// Close the handle once there is nothing left to run, so that it no longer keeps the loop alive.
+  fn close_if_idle(&self) {
+    let handle = unsafe { &mut *self.handle };
+
+    if self.tasks.borrow().is_empty() && !self.blocking.get() && !handle.is_closing() {
+      handle.close(None);
+    }
+  }
+}
+
+/// Resets the executor when `block_on()` returns or unwinds.
+struct Blocking(Rc<Executor>);
+
+impl Drop for Blocking {
+  fn drop(&mut self) {
+    self.0.blocking.set(false);
+    self.0.close_if_idle();
+  }
+}
+
+struct JoinState<T> {
+  output:   Option<T>,
+  finished: bool,
+  waker:    Option<Waker>,
+}
+
+/// Future resolving to the output of a task spawned with `Loop::spawn()`.
+///
+/// Dropping the handle detaches the task, which keeps running.
+pub struct JoinHandle<T> {
+  state: Rc<RefCell<JoinState<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+  /// Returns `true` once the task has completed.
+  pub fn is_finished(&self) -> bool {
+    self.state.borrow().finished
+  }
+}
+
+impl<T> Future for JoinHandle<T> {
+  type Output = T;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+    let mut state = self.state.borrow_mut();
+
+    if let Some(output) = state.output.take() {
+      return Poll::Ready(output);
+    }
+
+    assert!(!state.finished, "JoinHandle polled after completion");
+    state.waker = Some(cx.waker().clone());
+    Poll::Pending
+  }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("JoinHandle")
+      .field("finished", &self.is_finished())
+      .finish()
+  }
+}
+
+/// Value produced by a libuv callback, awaited by the futures of the crate.
+pub(crate) struct Oneshot<T> {
+  state: Rc<RefCell<(Option<T>, Option<Waker>)>>,
+}
+
+impl<T> Oneshot<T> {
+  pub(crate) fn new() -> Self {
+    Oneshot { state: Rc::new(RefCell::new((None, None))) }
+  }
+
+  /// Store the value and wake the future awaiting it.
+  pub(crate) fn set(&self, value: T) {
+    let waker = {
+      let mut state = self.state.borrow_mut();
+      state.0 = Some(value);
+      state.1.take()
+    };
+
+    if let Some(waker) = waker {
+      waker.wake();
+    }
+  }
+}
+
+impl<T> Clone for Oneshot<T> {
+  fn clone(&self) -> Self {
+    Oneshot { state: self.state.clone() }
+  }
+}
+
+impl<T> Future for Oneshot<T> {
+  type Output = T;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+    let mut state = self.state.borrow_mut();
+
+    if let Some(value) = state.0.take() {
+      return Poll::Ready(value);
+    }
+
+    state.1 = Some(cx.waker().clone());
+    Poll::Pending
+  }
+}
+
+impl Loop {
+  /// Spawn a future onto the loop. It is first polled on the next loop iteration, and the
+  /// loop stays alive until it completes.
+  ///
+  /// If the future panics, the loop is stopped and the panic is resumed from `Loop::run`.
+  pub fn spawn<F>(&self, future: F) -> Result<JoinHandle<F::Output>, Error>
+  where
+    F: Future + 'static
+  {
+    let executor = Executor::get(self)?;
+
+    let state = Rc::new(RefCell::new(JoinState {
+      output:   None,
+      finished: false,
+      waker:    None,
+    }));
+
+    let join = state.clone();
+
+    executor.spawn(Box::pin(async move {
+      let output = future.await;
+
+      let waker = {
+        let mut state = join.borrow_mut();
+        state.output = Some(output);
+        state.finished = true;
+        state.waker.take()
+      };
+
+      if let Some(waker) = waker {
+        waker.wake();
+      }
+    }));
+
+    Ok(JoinHandle { state })
+  }
+
+  /// Run the loop until `future` completes, and return its output.
+  /// Spawned tasks and other handles make progress in the meantime.
+  ///
+  /// Must not be called from a callback of the loop; a nested call fails with `EBUSY`.
+  pub fn block_on<F: Future>(&self, future: F) -> Result<F::Output, Error> {
+    let executor = Executor::get(self)?;
+
+    if executor.blocking.replace(true) {
+      return Err(Error::EBUSY);
+    }
+
+    let blocking = Blocking(executor);
+
+    let main = Arc::new(MainWaker {
+      woken:  AtomicBool::new(true),
+      sender: blocking.0.shared.sender.clone(),
+    });
+
+    let waker = Waker::from(main.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin::pin!(future);
+
+    loop {
+      if main.woken.swap(false, Ordering::AcqRel) {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+          return Ok(output);
+        }
+      }
+
+      self.run(RunMode::Once)?;
+    }
+  }
+}
+
+#[test]
+fn test_block_on_ready() {
+  let l = Loop::new().unwrap();
+  assert_eq!(l.block_on(async { 42 }), Ok(42));
+}
+
+#[test]
+fn test_block_on_woken_from_thread() {
+  let l = Loop::new().unwrap();
+  let woken = Arc::new(AtomicBool::new(false));
+  let mut thread = None;
+
+  let output = l.block_on(std::future::poll_fn(|cx| {
+    if woken.load(Ordering::Acquire) {
+      return Poll::Ready(7);
+    }
+
+    if thread.is_none() {
+      let (waker, woken) = (cx.waker().clone(), woken.clone());
+
+      thread = Some(std::thread::spawn(move || {
+        woken.store(true, Ordering::Release);
+        waker.wake();
+      }));
+    }
+
+    Poll::Pending
+  }));
+
+  assert_eq!(output, Ok(7));
+  thread.unwrap().join().unwrap();
+}
+
+#[test]
+fn test_spawn_dropped_join_handle() {
+  let l = Loop::new().unwrap();
+  let done = Rc::new(Cell::new(false));
+  let task = done.clone();
+
+  drop(l.spawn(async move { task.set(true) }).unwrap());
+  l.run(RunMode::Default).unwrap();
+
+  assert!(done.get());
+  assert_eq!(l.handles().len(), 0);
+}
+
+#[test]
+fn test_spawn_panic() {
+  let l = Loop::new().unwrap();
+  let join = l.spawn(async { panic!("task panicked") }).unwrap();
+
+  let payload = panic::catch_unwind(AssertUnwindSafe(|| l.run(RunMode::Default))).unwrap_err();
+  assert_eq!(payload.downcast_ref::<&str>(), Some(&"task panicked"));
+  assert!(!join.is_finished());
+}
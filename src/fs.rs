@@ -0,0 +1,993 @@
+//! File system operations.
+//!
+//! Every operation comes in two forms: a synchronous one, which runs on the calling thread
+//! (`open()`), and an asynchronous one which runs on the threadpool and reports its result
+//! to a callback on the loop thread (`open_async()`). In both cases the `uv_fs_t` request
+//! is released with `uv_fs_req_cleanup` once its result has been read.
+
+use std::ptr;
+use std::borrow::Cow;
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::os::raw::{c_char, c_int, c_void};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bitflags::bitflags;
+
+use crate::buf::Buf;
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::req::{Fs, FsCb};
+use crate::bindings::{
+  size_t, uv_loop_t, uv_fs_t, uv_file, uv_uid_t, uv_gid_t,
+  uv_stat_t, uv_statfs_t, uv_timespec_t, uv_dirent_t, uv_dirent_type_t,
+  uv_fs_req_cleanup, uv_fs_scandir_next,
+  uv_fs_open, uv_fs_close, uv_fs_read, uv_fs_write, uv_fs_unlink,
+  uv_fs_mkdir, uv_fs_mkdtemp, uv_fs_mkstemp, uv_fs_rmdir, uv_fs_scandir,
+  uv_fs_stat, uv_fs_fstat, uv_fs_lstat, uv_fs_statfs, uv_fs_rename,
+  uv_fs_fsync, uv_fs_fdatasync, uv_fs_ftruncate, uv_fs_copyfile, uv_fs_sendfile,
+  uv_fs_access, uv_fs_chmod, uv_fs_fchmod, uv_fs_utime, uv_fs_futime, uv_fs_lutime,
+  uv_fs_link, uv_fs_symlink, uv_fs_readlink, uv_fs_realpath,
+  uv_fs_chown, uv_fs_fchown, uv_fs_lchown,
+  UV_EOF, UV_ENOBUFS,
+  UV_FS_O_RDONLY, UV_FS_O_WRONLY, UV_FS_O_RDWR, UV_FS_O_APPEND, UV_FS_O_CREAT,
+  UV_FS_O_EXCL, UV_FS_O_TRUNC, UV_FS_O_DIRECT, UV_FS_O_DIRECTORY, UV_FS_O_DSYNC,
+  UV_FS_O_NOATIME, UV_FS_O_NOCTTY, UV_FS_O_NOFOLLOW, UV_FS_O_NONBLOCK, UV_FS_O_SYNC,
+  UV_FS_COPYFILE_EXCL, UV_FS_COPYFILE_FICLONE, UV_FS_COPYFILE_FICLONE_FORCE,
+  UV_FS_SYMLINK_DIR, UV_FS_SYMLINK_JUNCTION,
+  UV_DIRENT_UNKNOWN, UV_DIRENT_FILE, UV_DIRENT_DIR, UV_DIRENT_LINK,
+  UV_DIRENT_FIFO, UV_DIRENT_SOCKET, UV_DIRENT_CHAR, UV_DIRENT_BLOCK
+};
+
+pub type Fd = uv_file;
+pub type Uid = uv_uid_t;
+pub type Gid = uv_gid_t;
+
+bitflags! {
+  /// Flags for `open()`. Flags not supported by the platform are defined as `0`.
+  pub struct OpenFlags: c_int {
+    const RDONLY    = UV_FS_O_RDONLY as c_int;
+    const WRONLY    = UV_FS_O_WRONLY as c_int;
+    const RDWR      = UV_FS_O_RDWR as c_int;
+    const APPEND    = UV_FS_O_APPEND as c_int;
+    const CREAT     = UV_FS_O_CREAT as c_int;
+    const EXCL      = UV_FS_O_EXCL as c_int;
+    const TRUNC     = UV_FS_O_TRUNC as c_int;
+    const DIRECT    = UV_FS_O_DIRECT as c_int;
+    const DIRECTORY = UV_FS_O_DIRECTORY as c_int;
+    const DSYNC     = UV_FS_O_DSYNC as c_int;
+    const NOATIME   = UV_FS_O_NOATIME as c_int;
+    const NOCTTY    = UV_FS_O_NOCTTY as c_int;
+    const NOFOLLOW  = UV_FS_O_NOFOLLOW as c_int;
+    const NONBLOCK  = UV_FS_O_NONBLOCK as c_int;
+    const SYNC      = UV_FS_O_SYNC as c_int;
+  }
+}
+
+bitflags! {
+  /// Flags for `copyfile()`.
+  pub struct CopyFlags: c_int {
+    /// Fail with `EEXIST` if the destination path already exists.
+    const EXCL          = UV_FS_COPYFILE_EXCL as c_int;
+    /// Attempt to create a copy-on-write reflink, falling back to a regular copy.
+    const FICLONE       = UV_FS_COPYFILE_FICLONE as c_int;
+    /// Attempt to create a copy-on-write reflink, failing if it is not supported.
+    const FICLONE_FORCE = UV_FS_COPYFILE_FICLONE_FORCE as c_int;
+  }
+}
+
+bitflags! {
+  /// Flags for `symlink()`. Only meaningful on Windows.
+  pub struct SymlinkFlags: c_int {
+    /// The path is a directory.
+    const DIR       = UV_FS_SYMLINK_DIR as c_int;
+    /// Create the symlink using junction points.
+    const JUNCTION  = UV_FS_SYMLINK_JUNCTION as c_int;
+  }
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DirentType {
+  Unknown = UV_DIRENT_UNKNOWN,
+  File    = UV_DIRENT_FILE,
+  Dir     = UV_DIRENT_DIR,
+  Link    = UV_DIRENT_LINK,
+  Fifo    = UV_DIRENT_FIFO,
+  Socket  = UV_DIRENT_SOCKET,
+  Char    = UV_DIRENT_CHAR,
+  Block   = UV_DIRENT_BLOCK,
+}
+
+impl From<uv_dirent_type_t> for DirentType {
+  fn from(i: uv_dirent_type_t) -> Self {
+    match i {
+      UV_DIRENT_FILE    => DirentType::File,
+      UV_DIRENT_DIR     => DirentType::Dir,
+      UV_DIRENT_LINK    => DirentType::Link,
+      UV_DIRENT_FIFO    => DirentType::Fifo,
+      UV_DIRENT_SOCKET  => DirentType::Socket,
+      UV_DIRENT_CHAR    => DirentType::Char,
+      UV_DIRENT_BLOCK   => DirentType::Block,
+      _                 => DirentType::Unknown,
+    }
+  }
+}
+
+/// Directory entry returned by `scandir()` and `Dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+  pub name: OsString,
+  pub kind: DirentType,
+}
+
+impl DirEntry {
+  /// # Safety
+  /// `ent.name` must point to a valid nul-terminated string.
+  pub(crate) unsafe fn from_raw(ent: &uv_dirent_t) -> Self {
+    DirEntry {
+      name: bytes_to_os_str(CStr::from_ptr(ent.name).to_bytes()).into_owned(),
+      kind: DirentType::from(ent.type_),
+    }
+  }
+}
+
+/// Portable equivalent of `struct stat`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Stat {
+  pub dev:        u64,
+  pub mode:       u64,
+  pub nlink:      u64,
+  pub uid:        u64,
+  pub gid:        u64,
+  pub rdev:       u64,
+  pub ino:        u64,
+  pub size:       u64,
+  pub blksize:    u64,
+  pub blocks:     u64,
+  pub flags:      u64,
+  pub gen:        u64,
+  pub atime:      SystemTime,
+  pub mtime:      SystemTime,
+  pub ctime:      SystemTime,
+  pub birthtime:  SystemTime,
+}
+
+fn system_time(ts: &uv_timespec_t) -> SystemTime {
+  let nsec = Duration::from_nanos(ts.tv_nsec as u64);
+  if ts.tv_sec >= 0 {
+    UNIX_EPOCH + Duration::from_secs(ts.tv_sec as u64) + nsec
+  } else {
+    UNIX_EPOCH - Duration::from_secs(ts.tv_sec.unsigned_abs()) + nsec
+  }
+}
+
+fn timestamp(time: SystemTime) -> f64 {
+  match time.duration_since(UNIX_EPOCH) {
+    Ok(d) => d.as_secs_f64(),
+    Err(err) => -err.duration().as_secs_f64()
+  }
+}
+
+impl From<&uv_stat_t> for Stat {
+  fn from(st: &uv_stat_t) -> Self {
+    Stat {
+      dev:        st.st_dev,
+      mode:       st.st_mode,
+      nlink:      st.st_nlink,
+      uid:        st.st_uid,
+      gid:        st.st_gid,
+      rdev:       st.st_rdev,
+      ino:        st.st_ino,
+      size:       st.st_size,
+      blksize:    st.st_blksize,
+      blocks:     st.st_blocks,
+      flags:      st.st_flags,
+      gen:        st.st_gen,
+      atime:      system_time(&st.st_atim),
+      mtime:      system_time(&st.st_mtim),
+      ctime:      system_time(&st.st_ctim),
+      birthtime:  system_time(&st.st_birthtim),
+    }
+  }
+}
+
+impl Stat {
+  fn file_type(&self) -> libc::mode_t {
+    self.mode as libc::mode_t & libc::S_IFMT
+  }
+
+  pub fn is_file(&self) -> bool {
+    self.file_type() == libc::S_IFREG
+  }
+
+  pub fn is_dir(&self) -> bool {
+    self.file_type() == libc::S_IFDIR
+  }
+
+  pub fn is_symlink(&self) -> bool {
+    self.file_type() == libc::S_IFLNK
+  }
+}
+
+/// Portable equivalent of `struct statfs`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StatFs {
+  pub kind:   u64,
+  pub bsize:  u64,
+  pub blocks: u64,
+  pub bfree:  u64,
+  pub bavail: u64,
+  pub files:  u64,
+  pub ffree:  u64,
+}
+
+impl From<&uv_statfs_t> for StatFs {
+  fn from(st: &uv_statfs_t) -> Self {
+    StatFs {
+      kind:   st.f_type,
+      bsize:  st.f_bsize,
+      blocks: st.f_blocks,
+      bfree:  st.f_bfree,
+      bavail: st.f_bavail,
+      files:  st.f_files,
+      ffree:  st.f_ffree,
+    }
+  }
+}
+
+type FsFn = Box<dyn FnOnce(&mut Fs)>;
+
+/// Bytes of a path as expected by libuv: the raw bytes on unix, UTF-8 on Windows.
+#[cfg(unix)]
+fn os_str_to_bytes(s: &OsStr) -> Result<&[u8], Error> {
+  Ok(s.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn os_str_to_bytes(s: &OsStr) -> Result<&[u8], Error> {
+  s.to_str().map(str::as_bytes).ok_or(Error::EINVAL)
+}
+
+/// Path returned by libuv, as raw bytes on unix and UTF-8 on Windows.
+#[cfg(unix)]
+pub(crate) fn bytes_to_os_str(bytes: &[u8]) -> Cow<'_, OsStr> {
+  Cow::Borrowed(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn bytes_to_os_str(bytes: &[u8]) -> Cow<'_, OsStr> {
+  Cow::Owned(OsString::from(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+pub(crate) fn path_to_cstring<P: AsRef<Path>>(path: P) -> Result<CString, Error> {
+  CString::new(os_str_to_bytes(path.as_ref().as_os_str())?).map_err(|_| Error::EINVAL)
+}
+
+unsafe fn cstr_to_path(ptr: *const c_char) -> PathBuf {
+  PathBuf::from(bytes_to_os_str(CStr::from_ptr(ptr).to_bytes()).into_owned())
+}
+
+/// Read a path through one of the `*_getpath` functions, growing the buffer on `ENOBUFS`.
+pub(crate) fn getpath(get: impl Fn(*mut c_char, *mut size_t) -> c_int) -> Result<PathBuf, Error> {
+  let mut buf: Vec<u8> = vec![0; 256];
+
+  loop {
+    let mut size = buf.len() as size_t;
+    let rc = get(buf.as_mut_ptr() as *mut c_char, &mut size);
+
+    if rc == UV_ENOBUFS {
+      buf.resize(size as usize, 0);
+      continue;
+    }
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    buf.truncate(size as usize);
+    return Ok(PathBuf::from(bytes_to_os_str(&buf).into_owned()));
+  }
+}
+
+/// Run a request synchronously (null callback), then map and clean it up.
+pub(crate) fn run<T>(
+  l: &Loop,
+  submit: impl FnOnce(*mut uv_loop_t, *mut uv_fs_t, FsCb) -> c_int,
+  map: impl FnOnce(&mut Fs) -> Result<T, Error>
+) -> Result<T, Error> {
+  let mut req = Fs::default();
+
+  let rc = submit(l.as_raw(), req.as_mut_ptr(), None);
+  let ret = if rc < 0 {
+    Err(Error::from(rc))
+  } else {
+    map(&mut req)
+  };
+
+  req.cleanup();
+  ret
+}
+
+unsafe extern "C" fn fs_cb(req: *mut uv_fs_t) {
+  let mut req = Box::from_raw(req as *mut Fs);
+  let cb = Box::from_raw(req.data as *mut FsFn);
+  req.data = ptr::null_mut();
+
+  cb(&mut req);
+}
+
+/// Submit a request to the threadpool. The request and the closure are freed once `cb` ran.
+///
+/// The request is cleaned up before `cb` is called, so that `cb` may reuse or release
+/// whatever the request referenced (e.g. the entries buffer of a `Dir`).
+pub(crate) fn queue<T, M, F>(
+  l: &Loop,
+  submit: impl FnOnce(*mut uv_loop_t, *mut uv_fs_t, FsCb) -> c_int,
+  map: M,
+  cb: F
+) -> Result<(), Error>
+where
+  M: FnOnce(&mut Fs) -> Result<T, Error> + 'static,
+  F: FnOnce(Result<T, Error>) + 'static
+{
+  let done: FsFn = Box::new(move |req: &mut Fs| {
+    let ret = map(req);
+    req.cleanup();
+    cb(ret)
+  });
+  let req = Box::into_raw(Fs::boxed());
+
+  unsafe {
+    (*req).data = Box::into_raw(Box::new(done)) as *mut c_void;
+  }
+
+  let rc = submit(l.as_raw(), req, Some(fs_cb));
+
+  if rc < 0 {
+    unsafe {
+      drop(Box::from_raw((*req).data as *mut FsFn));
+      uv_fs_req_cleanup(req);
+      drop(Box::from_raw(req));
+    }
+    return Err(Error::from(rc));
+  }
+
+  Ok(())
+}
+
+pub(crate) fn map_usize(req: &mut Fs) -> Result<usize, Error> {
+  let rc = req.get_result();
+  if rc < 0 {
+    return Err(Error::from(rc as i32));
+  }
+  Ok(rc as usize)
+}
+
+fn map_unit(req: &mut Fs) -> Result<(), Error> {
+  map_usize(req).map(|_| ())
+}
+
+fn map_file(req: &mut Fs) -> Result<Fd, Error> {
+  map_usize(req).map(|fd| fd as Fd)
+}
+
+fn map_stat(req: &mut Fs) -> Result<Stat, Error> {
+  map_usize(req)?;
+  Ok(Stat::from(req.get_statbuf()))
+}
+
+fn map_statfs(req: &mut Fs) -> Result<StatFs, Error> {
+  map_usize(req)?;
+  let st = req.get_ptr() as *const uv_statfs_t;
+  Ok(StatFs::from(unsafe { &*st }))
+}
+
+/// Path returned through `req->ptr` (`readlink`, `realpath`).
+fn map_ptr_path(req: &mut Fs) -> Result<PathBuf, Error> {
+  map_usize(req)?;
+  Ok(unsafe { cstr_to_path(req.get_ptr() as *const c_char) })
+}
+
+/// Path returned through `req->path` (`mkdtemp`).
+fn map_path(req: &mut Fs) -> Result<PathBuf, Error> {
+  map_usize(req)?;
+  Ok(unsafe { cstr_to_path(req.get_path()) })
+}
+
+fn map_temp_file(req: &mut Fs) -> Result<(Fd, PathBuf), Error> {
+  let fd = map_file(req)?;
+  Ok((fd, unsafe { cstr_to_path(req.get_path()) }))
+}
+
+fn map_entries(req: &mut Fs) -> Result<Vec<DirEntry>, Error> {
+  let count = map_usize(req)?;
+  let mut entries = Vec::with_capacity(count);
+  let mut ent = uv_dirent_t::default();
+
+  loop {
+    let rc = unsafe {
+      uv_fs_scandir_next(req.as_mut_ptr(), &mut ent)
+    };
+
+    if rc == UV_EOF {
+      break;
+    }
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    entries.push(unsafe { DirEntry::from_raw(&ent) });
+  }
+
+  Ok(entries)
+}
+
+/// Equivalent to `open(2)`. Returns the opened file descriptor.
+pub fn open<P: AsRef<Path>>(l: &Loop, path: P, flags: OpenFlags, mode: c_int) -> Result<Fd, Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_open(l, req, path.as_ptr(), flags.bits(), mode, cb) }, map_file)
+}
+
+/// Asynchronous `open()`.
+pub fn open_async<P, F>(l: &Loop, path: P, flags: OpenFlags, mode: c_int, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<Fd, Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_open(l, req, path.as_ptr(), flags.bits(), mode, fs_cb) }, map_file, cb)
+}
+
+/// Equivalent to `close(2)`.
+pub fn close(l: &Loop, file: Fd) -> Result<(), Error> {
+  run(l, |l, req, cb| unsafe { uv_fs_close(l, req, file, cb) }, map_unit)
+}
+
+/// Asynchronous `close()`.
+pub fn close_async<F>(l: &Loop, file: Fd, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_close(l, req, file, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `preadv(2)`. If `offset` is negative, the current file position is used and updated.
+/// Returns the number of bytes read, `0` at end of file.
+pub fn read(l: &Loop, file: Fd, buf: &mut [u8], offset: i64) -> Result<usize, Error> {
+  let bufs = [Buf { base: buf.as_mut_ptr() as *mut c_char, len: buf.len() as size_t }];
+  run(l, |l, req, cb| unsafe { uv_fs_read(l, req, file, bufs.as_ptr(), 1, offset, cb) }, map_usize)
+}
+
+/// Asynchronous `read()` of at most `len` bytes. The callback receives the bytes read.
+pub fn read_async<F>(l: &Loop, file: Fd, len: usize, offset: i64, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<Vec<u8>, Error>) + 'static
+{
+  let mut data = vec![0u8; len];
+  let bufs = [Buf { base: data.as_mut_ptr() as *mut c_char, len: len as size_t }];
+
+  let map = move |req: &mut Fs| {
+    let n = map_usize(req)?;
+    data.truncate(n);
+    Ok(data)
+  };
+
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_read(l, req, file, bufs.as_ptr(), 1, offset, fs_cb) }, map, cb)
+}
+
+/// Equivalent to `pwritev(2)`. If `offset` is negative, the current file position is used and updated.
+/// Returns the number of bytes written.
+pub fn write(l: &Loop, file: Fd, data: &[u8], offset: i64) -> Result<usize, Error> {
+  let bufs = [Buf::from(data)];
+  run(l, |l, req, cb| unsafe { uv_fs_write(l, req, file, bufs.as_ptr(), 1, offset, cb) }, map_usize)
+}
+
+/// Asynchronous `write()`. `data` is kept alive until the callback runs.
+pub fn write_async<F>(l: &Loop, file: Fd, data: Vec<u8>, offset: i64, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<usize, Error>) + 'static
+{
+  let bufs = [Buf::from(&data[..])];
+
+  let map = move |req: &mut Fs| {
+    let _data = data;
+    map_usize(req)
+  };
+
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_write(l, req, file, bufs.as_ptr(), 1, offset, fs_cb) }, map, cb)
+}
+
+/// Equivalent to `unlink(2)`.
+pub fn unlink<P: AsRef<Path>>(l: &Loop, path: P) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_unlink(l, req, path.as_ptr(), cb) }, map_unit)
+}
+
+/// Asynchronous `unlink()`.
+pub fn unlink_async<P, F>(l: &Loop, path: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_unlink(l, req, path.as_ptr(), fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `mkdir(2)`.
+pub fn mkdir<P: AsRef<Path>>(l: &Loop, path: P, mode: c_int) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_mkdir(l, req, path.as_ptr(), mode, cb) }, map_unit)
+}
+
+/// Asynchronous `mkdir()`.
+pub fn mkdir_async<P, F>(l: &Loop, path: P, mode: c_int, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_mkdir(l, req, path.as_ptr(), mode, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `mkdtemp(3)`. `tpl` must end with `XXXXXX`. Returns the path of the created directory.
+pub fn mkdtemp<P: AsRef<Path>>(l: &Loop, tpl: P) -> Result<PathBuf, Error> {
+  let tpl = path_to_cstring(tpl)?;
+  run(l, |l, req, cb| unsafe { uv_fs_mkdtemp(l, req, tpl.as_ptr(), cb) }, map_path)
+}
+
+/// Asynchronous `mkdtemp()`.
+pub fn mkdtemp_async<P, F>(l: &Loop, tpl: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<PathBuf, Error>) + 'static
+{
+  let tpl = path_to_cstring(tpl)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_mkdtemp(l, req, tpl.as_ptr(), fs_cb) }, map_path, cb)
+}
+
+/// Equivalent to `mkstemp(3)`. `tpl` must end with `XXXXXX`.
+/// Returns the file descriptor and the path of the created file.
+pub fn mkstemp<P: AsRef<Path>>(l: &Loop, tpl: P) -> Result<(Fd, PathBuf), Error> {
+  let tpl = path_to_cstring(tpl)?;
+  run(l, |l, req, cb| unsafe { uv_fs_mkstemp(l, req, tpl.as_ptr(), cb) }, map_temp_file)
+}
+
+/// Asynchronous `mkstemp()`.
+pub fn mkstemp_async<P, F>(l: &Loop, tpl: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(Fd, PathBuf), Error>) + 'static
+{
+  let tpl = path_to_cstring(tpl)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_mkstemp(l, req, tpl.as_ptr(), fs_cb) }, map_temp_file, cb)
+}
+
+/// Equivalent to `rmdir(2)`.
+pub fn rmdir<P: AsRef<Path>>(l: &Loop, path: P) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_rmdir(l, req, path.as_ptr(), cb) }, map_unit)
+}
+
+/// Asynchronous `rmdir()`.
+pub fn rmdir_async<P, F>(l: &Loop, path: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_rmdir(l, req, path.as_ptr(), fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `scandir(3)`, with a slightly different API. `.` and `..` are not included.
+pub fn scandir<P: AsRef<Path>>(l: &Loop, path: P) -> Result<Vec<DirEntry>, Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_scandir(l, req, path.as_ptr(), 0, cb) }, map_entries)
+}
+
+/// Asynchronous `scandir()`.
+pub fn scandir_async<P, F>(l: &Loop, path: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<Vec<DirEntry>, Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_scandir(l, req, path.as_ptr(), 0, fs_cb) }, map_entries, cb)
+}
+
+/// Equivalent to `stat(2)`.
+pub fn stat<P: AsRef<Path>>(l: &Loop, path: P) -> Result<Stat, Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_stat(l, req, path.as_ptr(), cb) }, map_stat)
+}
+
+/// Asynchronous `stat()`.
+pub fn stat_async<P, F>(l: &Loop, path: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<Stat, Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_stat(l, req, path.as_ptr(), fs_cb) }, map_stat, cb)
+}
+
+/// Equivalent to `fstat(2)`.
+pub fn fstat(l: &Loop, file: Fd) -> Result<Stat, Error> {
+  run(l, |l, req, cb| unsafe { uv_fs_fstat(l, req, file, cb) }, map_stat)
+}
+
+/// Asynchronous `fstat()`.
+pub fn fstat_async<F>(l: &Loop, file: Fd, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<Stat, Error>) + 'static
+{
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_fstat(l, req, file, fs_cb) }, map_stat, cb)
+}
+
+/// Equivalent to `lstat(2)`.
+pub fn lstat<P: AsRef<Path>>(l: &Loop, path: P) -> Result<Stat, Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_lstat(l, req, path.as_ptr(), cb) }, map_stat)
+}
+
+/// Asynchronous `lstat()`.
+pub fn lstat_async<P, F>(l: &Loop, path: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<Stat, Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_lstat(l, req, path.as_ptr(), fs_cb) }, map_stat, cb)
+}
+
+/// Equivalent to `statfs(2)`.
+pub fn statfs<P: AsRef<Path>>(l: &Loop, path: P) -> Result<StatFs, Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_statfs(l, req, path.as_ptr(), cb) }, map_statfs)
+}
+
+/// Asynchronous `statfs()`.
+pub fn statfs_async<P, F>(l: &Loop, path: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<StatFs, Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_statfs(l, req, path.as_ptr(), fs_cb) }, map_statfs, cb)
+}
+
+/// Equivalent to `rename(2)`.
+pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(l: &Loop, path: P, new_path: Q) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  let new_path = path_to_cstring(new_path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_rename(l, req, path.as_ptr(), new_path.as_ptr(), cb) }, map_unit)
+}
+
+/// Asynchronous `rename()`.
+pub fn rename_async<P, Q, F>(l: &Loop, path: P, new_path: Q, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  Q: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  let new_path = path_to_cstring(new_path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_rename(l, req, path.as_ptr(), new_path.as_ptr(), fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `fsync(2)`.
+pub fn fsync(l: &Loop, file: Fd) -> Result<(), Error> {
+  run(l, |l, req, cb| unsafe { uv_fs_fsync(l, req, file, cb) }, map_unit)
+}
+
+/// Asynchronous `fsync()`.
+pub fn fsync_async<F>(l: &Loop, file: Fd, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_fsync(l, req, file, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `fdatasync(2)`.
+pub fn fdatasync(l: &Loop, file: Fd) -> Result<(), Error> {
+  run(l, |l, req, cb| unsafe { uv_fs_fdatasync(l, req, file, cb) }, map_unit)
+}
+
+/// Asynchronous `fdatasync()`.
+pub fn fdatasync_async<F>(l: &Loop, file: Fd, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_fdatasync(l, req, file, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `ftruncate(2)`.
+pub fn ftruncate(l: &Loop, file: Fd, offset: i64) -> Result<(), Error> {
+  run(l, |l, req, cb| unsafe { uv_fs_ftruncate(l, req, file, offset, cb) }, map_unit)
+}
+
+/// Asynchronous `ftruncate()`.
+pub fn ftruncate_async<F>(l: &Loop, file: Fd, offset: i64, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_ftruncate(l, req, file, offset, fs_cb) }, map_unit, cb)
+}
+
+/// Copies a file from `path` to `new_path`.
+/// If the destination exists it is overwritten, unless `CopyFlags::EXCL` is given.
+pub fn copyfile<P, Q>(l: &Loop, path: P, new_path: Q, flags: CopyFlags) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  Q: AsRef<Path>
+{
+  let path = path_to_cstring(path)?;
+  let new_path = path_to_cstring(new_path)?;
+  run(l, |l, req, cb| unsafe {
+    uv_fs_copyfile(l, req, path.as_ptr(), new_path.as_ptr(), flags.bits(), cb)
+  }, map_unit)
+}
+
+/// Asynchronous `copyfile()`.
+pub fn copyfile_async<P, Q, F>(l: &Loop, path: P, new_path: Q, flags: CopyFlags, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  Q: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  let new_path = path_to_cstring(new_path)?;
+  queue(l, |l, req, fs_cb| unsafe {
+    uv_fs_copyfile(l, req, path.as_ptr(), new_path.as_ptr(), flags.bits(), fs_cb)
+  }, map_unit, cb)
+}
+
+/// Limited equivalent to `sendfile(2)`. Returns the number of bytes copied.
+pub fn sendfile(l: &Loop, out_fd: Fd, in_fd: Fd, in_offset: i64, length: usize) -> Result<usize, Error> {
+  run(l, |l, req, cb| unsafe {
+    uv_fs_sendfile(l, req, out_fd, in_fd, in_offset, length as size_t, cb)
+  }, map_usize)
+}
+
+/// Asynchronous `sendfile()`.
+pub fn sendfile_async<F>(l: &Loop, out_fd: Fd, in_fd: Fd, in_offset: i64, length: usize, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<usize, Error>) + 'static
+{
+  queue(l, |l, req, fs_cb| unsafe {
+    uv_fs_sendfile(l, req, out_fd, in_fd, in_offset, length as size_t, fs_cb)
+  }, map_usize, cb)
+}
+
+/// Equivalent to `access(2)` on Unix. `mode` is a mask of `F_OK`, `R_OK`, `W_OK` and `X_OK`.
+pub fn access<P: AsRef<Path>>(l: &Loop, path: P, mode: c_int) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_access(l, req, path.as_ptr(), mode, cb) }, map_unit)
+}
+
+/// Asynchronous `access()`.
+pub fn access_async<P, F>(l: &Loop, path: P, mode: c_int, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_access(l, req, path.as_ptr(), mode, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `chmod(2)`.
+pub fn chmod<P: AsRef<Path>>(l: &Loop, path: P, mode: c_int) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_chmod(l, req, path.as_ptr(), mode, cb) }, map_unit)
+}
+
+/// Asynchronous `chmod()`.
+pub fn chmod_async<P, F>(l: &Loop, path: P, mode: c_int, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_chmod(l, req, path.as_ptr(), mode, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `fchmod(2)`.
+pub fn fchmod(l: &Loop, file: Fd, mode: c_int) -> Result<(), Error> {
+  run(l, |l, req, cb| unsafe { uv_fs_fchmod(l, req, file, mode, cb) }, map_unit)
+}
+
+/// Asynchronous `fchmod()`.
+pub fn fchmod_async<F>(l: &Loop, file: Fd, mode: c_int, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_fchmod(l, req, file, mode, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `utime(2)`.
+pub fn utime<P: AsRef<Path>>(l: &Loop, path: P, atime: SystemTime, mtime: SystemTime) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  let (atime, mtime) = (timestamp(atime), timestamp(mtime));
+  run(l, |l, req, cb| unsafe { uv_fs_utime(l, req, path.as_ptr(), atime, mtime, cb) }, map_unit)
+}
+
+/// Asynchronous `utime()`.
+pub fn utime_async<P, F>(l: &Loop, path: P, atime: SystemTime, mtime: SystemTime, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  let (atime, mtime) = (timestamp(atime), timestamp(mtime));
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_utime(l, req, path.as_ptr(), atime, mtime, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `futimes(3)`.
+pub fn futime(l: &Loop, file: Fd, atime: SystemTime, mtime: SystemTime) -> Result<(), Error> {
+  let (atime, mtime) = (timestamp(atime), timestamp(mtime));
+  run(l, |l, req, cb| unsafe { uv_fs_futime(l, req, file, atime, mtime, cb) }, map_unit)
+}
+
+/// Asynchronous `futime()`.
+pub fn futime_async<F>(l: &Loop, file: Fd, atime: SystemTime, mtime: SystemTime, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let (atime, mtime) = (timestamp(atime), timestamp(mtime));
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_futime(l, req, file, atime, mtime, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `lutimes(3)`. Changes the timestamps of a symbolic link rather than its target.
+pub fn lutime<P: AsRef<Path>>(l: &Loop, path: P, atime: SystemTime, mtime: SystemTime) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  let (atime, mtime) = (timestamp(atime), timestamp(mtime));
+  run(l, |l, req, cb| unsafe { uv_fs_lutime(l, req, path.as_ptr(), atime, mtime, cb) }, map_unit)
+}
+
+/// Asynchronous `lutime()`.
+pub fn lutime_async<P, F>(l: &Loop, path: P, atime: SystemTime, mtime: SystemTime, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  let (atime, mtime) = (timestamp(atime), timestamp(mtime));
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_lutime(l, req, path.as_ptr(), atime, mtime, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `link(2)`.
+pub fn link<P: AsRef<Path>, Q: AsRef<Path>>(l: &Loop, path: P, new_path: Q) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  let new_path = path_to_cstring(new_path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_link(l, req, path.as_ptr(), new_path.as_ptr(), cb) }, map_unit)
+}
+
+/// Asynchronous `link()`.
+pub fn link_async<P, Q, F>(l: &Loop, path: P, new_path: Q, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  Q: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  let new_path = path_to_cstring(new_path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_link(l, req, path.as_ptr(), new_path.as_ptr(), fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `symlink(2)`.
+pub fn symlink<P, Q>(l: &Loop, path: P, new_path: Q, flags: SymlinkFlags) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  Q: AsRef<Path>
+{
+  let path = path_to_cstring(path)?;
+  let new_path = path_to_cstring(new_path)?;
+  run(l, |l, req, cb| unsafe {
+    uv_fs_symlink(l, req, path.as_ptr(), new_path.as_ptr(), flags.bits(), cb)
+  }, map_unit)
+}
+
+/// Asynchronous `symlink()`.
+pub fn symlink_async<P, Q, F>(l: &Loop, path: P, new_path: Q, flags: SymlinkFlags, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  Q: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  let new_path = path_to_cstring(new_path)?;
+  queue(l, |l, req, fs_cb| unsafe {
+    uv_fs_symlink(l, req, path.as_ptr(), new_path.as_ptr(), flags.bits(), fs_cb)
+  }, map_unit, cb)
+}
+
+/// Equivalent to `readlink(2)`.
+pub fn readlink<P: AsRef<Path>>(l: &Loop, path: P) -> Result<PathBuf, Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_readlink(l, req, path.as_ptr(), cb) }, map_ptr_path)
+}
+
+/// Asynchronous `readlink()`.
+pub fn readlink_async<P, F>(l: &Loop, path: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<PathBuf, Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_readlink(l, req, path.as_ptr(), fs_cb) }, map_ptr_path, cb)
+}
+
+/// Equivalent to `realpath(3)`.
+pub fn realpath<P: AsRef<Path>>(l: &Loop, path: P) -> Result<PathBuf, Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_realpath(l, req, path.as_ptr(), cb) }, map_ptr_path)
+}
+
+/// Asynchronous `realpath()`.
+pub fn realpath_async<P, F>(l: &Loop, path: P, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<PathBuf, Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_realpath(l, req, path.as_ptr(), fs_cb) }, map_ptr_path, cb)
+}
+
+/// Equivalent to `chown(2)`.
+pub fn chown<P: AsRef<Path>>(l: &Loop, path: P, uid: Uid, gid: Gid) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_chown(l, req, path.as_ptr(), uid, gid, cb) }, map_unit)
+}
+
+/// Asynchronous `chown()`.
+pub fn chown_async<P, F>(l: &Loop, path: P, uid: Uid, gid: Gid, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_chown(l, req, path.as_ptr(), uid, gid, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `fchown(2)`.
+pub fn fchown(l: &Loop, file: Fd, uid: Uid, gid: Gid) -> Result<(), Error> {
+  run(l, |l, req, cb| unsafe { uv_fs_fchown(l, req, file, uid, gid, cb) }, map_unit)
+}
+
+/// Asynchronous `fchown()`.
+pub fn fchown_async<F>(l: &Loop, file: Fd, uid: Uid, gid: Gid, cb: F) -> Result<(), Error>
+where
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_fchown(l, req, file, uid, gid, fs_cb) }, map_unit, cb)
+}
+
+/// Equivalent to `lchown(2)`.
+pub fn lchown<P: AsRef<Path>>(l: &Loop, path: P, uid: Uid, gid: Gid) -> Result<(), Error> {
+  let path = path_to_cstring(path)?;
+  run(l, |l, req, cb| unsafe { uv_fs_lchown(l, req, path.as_ptr(), uid, gid, cb) }, map_unit)
+}
+
+/// Asynchronous `lchown()`.
+pub fn lchown_async<P, F>(l: &Loop, path: P, uid: Uid, gid: Gid, cb: F) -> Result<(), Error>
+where
+  P: AsRef<Path>,
+  F: FnOnce(Result<(), Error>) + 'static
+{
+  let path = path_to_cstring(path)?;
+  queue(l, |l, req, fs_cb| unsafe { uv_fs_lchown(l, req, path.as_ptr(), uid, gid, fs_cb) }, map_unit, cb)
+}
+
+pub mod dir;
+pub use self::dir::Dir;
+
+#[test]
+fn test_stat_times() {
+  let ts = uv_timespec_t { tv_sec: 1, tv_nsec: 500 };
+  assert_eq!(system_time(&ts), UNIX_EPOCH + Duration::new(1, 500));
+
+  let ts = uv_timespec_t { tv_sec: -2, tv_nsec: 500_000_000 };
+  assert_eq!(timestamp(system_time(&ts)), -1.5);
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::mem::{self, ManuallyDrop};
+use std::path::Path;
+use std::collections::VecDeque;
+
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::req::Fs;
+use crate::fs::{DirEntry, path_to_cstring, run, queue, map_usize};
+use crate::bindings::{
+  size_t, uv_dir_t, uv_dirent_t,
+  uv_fs_opendir, uv_fs_readdir, uv_fs_closedir
+};
+
+/// Number of entries read per `uv_fs_readdir` call unless configured otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// Directory stream over `uv_dir_t`, reading entries in batches.
+///
+/// The directory is closed when the `Dir` is dropped.
+pub struct Dir<'l> {
+  dir:      *mut uv_dir_t,
+  loop_:    &'l Loop,
+  dirents:  Vec<uv_dirent_t>,
+  pending:  VecDeque<DirEntry>,
+  done:     bool,
+}
+
+fn map_dir(req: &mut Fs) -> Result<*mut uv_dir_t, Error> {
+  map_usize(req)?;
+  Ok(req.get_ptr() as *mut uv_dir_t)
+}
+
+/// Extend the loop borrow of a value travelling with a request, which completes on the loop.
+///
+/// # Safety
+/// The loop must outlive the request.
+unsafe fn extend(l: &Loop) -> &'static Loop {
+  &*(l as *const Loop)
+}
+
+impl<'l> Dir<'l> {
+  fn new(l: &'l Loop, dir: *mut uv_dir_t) -> Self {
+    Dir {
+      dir,
+      loop_:    l,
+      dirents:  vec![uv_dirent_t::default(); DEFAULT_BATCH_SIZE],
+      pending:  VecDeque::new(),
+      done:     false,
+    }
+  }
+
+  /// Opens `path` as a directory stream.
+  pub fn open<P: AsRef<Path>>(l: &'l Loop, path: P) -> Result<Self, Error> {
+    let path = path_to_cstring(path)?;
+    let dir = run(l, |l, req, cb| unsafe { uv_fs_opendir(l, req, path.as_ptr(), cb) }, map_dir)?;
+    Ok(Dir::new(l, dir))
+  }
+
+  /// Asynchronous `open()`.
+  pub fn open_async<P, F>(l: &'l Loop, path: P, cb: F) -> Result<(), Error>
+  where
+    P: AsRef<Path>,
+    F: FnOnce(Result<Dir<'l>, Error>) + 'static
+  {
+    let path = path_to_cstring(path)?;
+    let lp = unsafe { extend(l) };
+    queue(l, |l, req, fs_cb| unsafe { uv_fs_opendir(l, req, path.as_ptr(), fs_cb) }, map_dir, move |dir| {
+      cb(dir.map(|dir| Dir::new(lp, dir)))
+    })
+  }
+
+  /// Same directory, with its loop borrow extended to travel with a request.
+  ///
+  /// # Safety
+  /// The loop must outlive the request.
+  unsafe fn into_static(self) -> Dir<'static> {
+    let mut this = ManuallyDrop::new(self);
+
+    Dir {
+      dir:      this.dir,
+      loop_:    extend(this.loop_),
+      dirents:  mem::take(&mut this.dirents),
+      pending:  mem::take(&mut this.pending),
+      done:     this.done,
+    }
+  }
+
+  /// Number of entries requested from libuv on each read.
+  pub fn batch_size(&self) -> usize {
+    self.dirents.len()
+  }
+
+  /// Set the number of entries requested from libuv on each read. Must be greater than `0`.
+  pub fn set_batch_size(&mut self, size: usize) -> Result<(), Error> {
+    if size == 0 {
+      return Err(Error::EINVAL);
+    }
+    self.dirents.resize(size, uv_dirent_t::default());
+    Ok(())
+  }
+
+  fn prepare(&mut self) {
+    unsafe {
+      (*self.dir).dirents = self.dirents.as_mut_ptr();
+      (*self.dir).nentries = self.dirents.len() as size_t;
+    }
+  }
+
+  /// Copies the entries filled by a `readdir` request, before `uv_fs_req_cleanup` frees them.
+  fn entries(&self, req: &mut Fs) -> Result<Vec<DirEntry>, Error> {
+    let count = map_usize(req)?;
+    Ok(self.dirents[..count].iter().map(|ent| unsafe { DirEntry::from_raw(ent) }).collect())
+  }
+
+  /// Reads the next batch of entries. An empty batch means the end of the directory was reached.
+  /// `.` and `..` are not included.
+  pub fn read(&mut self) -> Result<Vec<DirEntry>, Error> {
+    self.prepare();
+    let dir = self.dir;
+    let l = self.loop_;
+    run(l, |l, req, cb| unsafe { uv_fs_readdir(l, req, dir, cb) }, |req| self.entries(req))
+  }
+
+  /// Asynchronous `read()`. The `Dir` is handed back to the callback along with the batch.
+  /// If the request cannot be submitted, the directory is closed and the error returned.
+  pub fn read_async<F>(mut self, cb: F) -> Result<(), Error>
+  where
+    F: FnOnce(Dir<'l>, Result<Vec<DirEntry>, Error>) + 'static
+  {
+    self.prepare();
+    let dir = self.dir;
+    let l = self.loop_;
+    let this = unsafe { self.into_static() };
+
+    let map = move |req: &mut Fs| {
+      let entries = this.entries(req);
+      Ok((this, entries))
+    };
+
+    queue(l, |l, req, fs_cb| unsafe { uv_fs_readdir(l, req, dir, fs_cb) }, map, move |ret| {
+      if let Ok((dir, entries)) = ret {
+        cb(dir, entries)
+      }
+    })
+  }
+
+  /// Closes the directory stream.
+  pub fn close(mut self) -> Result<(), Error> {
+    self.close_dir()
+  }
+
+  /// Asynchronous `close()`.
+  pub fn close_async<F>(mut self, cb: F) -> Result<(), Error>
+  where
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    let dir = std::mem::replace(&mut self.dir, std::ptr::null_mut());
+    let rc = queue(self.loop_, |l, req, fs_cb| unsafe { uv_fs_closedir(l, req, dir, fs_cb) }, map_usize, move |ret| {
+      cb(ret.map(|_| ()))
+    });
+
+    // Closed synchronously on drop instead.
+    if rc.is_err() {
+      self.dir = dir;
+    }
+
+    rc
+  }
+
+  fn close_dir(&mut self) -> Result<(), Error> {
+    if self.dir.is_null() {
+      return Ok(());
+    }
+
+    let dir = std::mem::replace(&mut self.dir, std::ptr::null_mut());
+    run(self.loop_, |l, req, cb| unsafe { uv_fs_closedir(l, req, dir, cb) }, map_usize).map(|_| ())
+  }
+}
+
+impl Iterator for Dir<'_> {
+  type Item = Result<DirEntry, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if let Some(entry) = self.pending.pop_front() {
+      return Some(Ok(entry));
+    }
+
+    if self.done {
+      return None;
+    }
+
+    match self.read() {
+      Ok(entries) if entries.is_empty() => {
+        self.done = true;
+        None
+      },
+      Ok(entries) => {
+        self.pending.extend(entries);
+        self.pending.pop_front().map(Ok)
+      },
+      Err(err) => {
+        self.done = true;
+        Some(Err(err))
+      }
+    }
+  }
+}
+
+impl Drop for Dir<'_> {
+  fn drop(&mut self) {
+    let _ = self.close_dir();
+  }
+}
+
+impl fmt::Debug for Dir<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("Dir")
+      .field("dir",         &self.dir)
+      .field("loop",        &self.loop_.as_ptr())
+      .field("batch_size",  &self.dirents.len())
+      .field("pending",     &self.pending.len())
+      .field("done",        &self.done)
+      .finish()
+  }
+}
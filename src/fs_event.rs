@@ -0,0 +1,176 @@
+use std::fmt;
+use std::ffi::{CStr, OsStr};
+use std::path::{Path, PathBuf};
+use std::os::raw::{c_char, c_int, c_uint};
+use bitflags::bitflags;
+
+use crate::fs;
+use crate::context::{self, Slot};
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::handle::{Handle, HandleImpl};
+use crate::bindings::{
+  uv_fs_event_t, uv_fs_event_cb, uv_fs_event, uv_fs_event_flags, uv_handle_t,
+  uv_fs_event_init, uv_fs_event_start, uv_fs_event_stop, uv_fs_event_getpath,
+  UV_RENAME, UV_CHANGE,
+  UV_FS_EVENT_WATCH_ENTRY, UV_FS_EVENT_STAT, UV_FS_EVENT_RECURSIVE
+};
+
+pub type FsEventCb = uv_fs_event_cb;
+
+type FsEventFn = Box<dyn FnMut(&mut FsEvent, Option<&OsStr>, Result<FsEventKind, Error>)>;
+
+repr!{
+  pub type FsEvent = uv_fs_event_t;
+}
+
+bitflags! {
+  /// Event types reported to the callback.
+  pub struct FsEventKind: uv_fs_event {
+    const RENAME  = UV_RENAME;
+    const CHANGE  = UV_CHANGE;
+  }
+}
+
+bitflags! {
+  /// Flags that can be passed to `start()` to control its behavior.
+  pub struct FsEventFlags: uv_fs_event_flags {
+    /// Only watch the directory entry itself, not its content. Currently unused.
+    const WATCH_ENTRY = UV_FS_EVENT_WATCH_ENTRY;
+    /// Use `stat()` polling on platforms where the native watcher is unreliable.
+    /// Currently unused.
+    const STAT        = UV_FS_EVENT_STAT;
+    /// Watch for changes in subdirectories too. Only supported on macOS and Windows;
+    /// `start()` fails with `ENOSYS` elsewhere.
+    const RECURSIVE   = UV_FS_EVENT_RECURSIVE;
+  }
+}
+
+unsafe extern "C" fn fs_event_cb(handle: *mut uv_fs_event_t, filename: *const c_char, events: c_int, status: c_int) {
+  let filename = if filename.is_null() {
+    None
+  } else {
+    Some(fs::bytes_to_os_str(CStr::from_ptr(filename).to_bytes()))
+  };
+
+  let events = if status < 0 {
+    Err(Error::from(status))
+  } else {
+    Ok(FsEventKind::from_bits_truncate(events as uv_fs_event))
+  };
+
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut FsEventFn| {
+    cb(&mut *(handle as *mut FsEvent), filename.as_deref(), events)
+  });
+}
+
+impl FsEvent {
+  /// Initialize the handle.
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_fs_event_init(l.as_raw(), self.as_mut_ptr())
+    };
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+    Ok(())
+  }
+
+  /// Start the handle with the given callback, which will watch the specified path for changes.
+  pub fn start<P: AsRef<Path>>(&mut self, cb: FsEventCb, path: P, flags: FsEventFlags) -> Result<(), Error> {
+    let path = fs::path_to_cstring(path)?;
+    let rc = unsafe {
+      uv_fs_event_start(self.as_mut_ptr(), cb, path.as_ptr(), flags.bits() as c_uint)
+    };
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+    Ok(())
+  }
+
+  /// Same as `start()`, with a closure as callback.
+  ///
+  /// The closure receives the name of the file that changed (relative to the watched
+  /// directory, when a directory is watched) and the events, or the error reported by libuv.
+  pub fn start_with<P, F>(&mut self, path: P, flags: FsEventFlags, cb: F) -> Result<(), Error>
+  where
+    P: AsRef<Path>,
+    F: FnMut(&mut FsEvent, Option<&OsStr>, Result<FsEventKind, Error>) + 'static
+  {
+    self.start(Some(fs_event_cb), path, flags)?;
+    context::set::<FsEventFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
+  /// Stop the handle, the callback will no longer be called.
+  pub fn stop(&mut self) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_fs_event_stop(self.as_mut_ptr())
+    };
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+    Ok(())
+  }
+
+  /// Get the path being monitored by the handle. Fails with `EINVAL` if the handle is not active.
+  pub fn getpath(&mut self) -> Result<PathBuf, Error> {
+    let handle = self.as_mut_ptr();
+    fs::getpath(|buf, size| unsafe { uv_fs_event_getpath(handle, buf, size) })
+  }
+}
+
+impl HandleImpl for FsEvent {
+  fn as_handle(&self) -> &Handle {
+    unsafe {
+      let ptr = self.as_ptr() as *const Handle;
+      ptr.as_ref().unwrap()
+    }
+  }
+  fn as_mut_handle(&mut self) -> &mut Handle {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Handle;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+impl fmt::Debug for FsEvent {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("FsEvent")
+      .field("data",  &self.data)
+      .field("loop",  &self.loop_)
+      .field("type",  &self.type_)
+      .field("close_cb",  &self.close_cb)
+      .field("handle_queue",  &self.handle_queue)
+      .field("u",  unsafe { &self.u.fd })
+      .field("next_closing",  &self.next_closing)
+      .field("flags",  &self.flags)
+      .field("path",  &self.path)
+      .field("cb",  &self.cb)
+      .field("watchers",  &self.watchers)
+      .field("wd",  &self.wd)
+      .finish()
+  }
+}
+
+#[test]
+fn test_layout_fs_event() {
+  use std::mem;
+  assert_eq!(
+    mem::size_of::<FsEvent>(),
+    mem::size_of::<uv_fs_event_t>(),
+    concat!("Size of: ", stringify!(FsEvent))
+  );
+  assert_eq!(
+    mem::align_of::<FsEvent>(),
+    mem::align_of::<uv_fs_event_t>(),
+    concat!("Alignment of ", stringify!(FsEvent))
+  );
+  assert_eq!(
+    mem::align_of::<FsEvent>(),
+    mem::align_of::<Handle>(),
+    concat!("Alignment of ", stringify!(FsEvent))
+  );
+}
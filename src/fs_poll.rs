@@ -0,0 +1,147 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::os::raw::{c_int, c_uint};
+
+use crate::fs::{self, Stat};
+use crate::context::{self, Slot};
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::handle::{Handle, HandleImpl};
+use crate::bindings::{
+  uv_fs_poll_t, uv_fs_poll_cb, uv_stat_t, uv_handle_t,
+  uv_fs_poll_init, uv_fs_poll_start, uv_fs_poll_stop, uv_fs_poll_getpath
+};
+
+pub type FsPollCb = uv_fs_poll_cb;
+
+type FsPollFn = Box<dyn FnMut(&mut FsPoll, Result<(), Error>, &Stat, &Stat)>;
+
+repr!{
+  pub type FsPoll = uv_fs_poll_t;
+}
+
+unsafe extern "C" fn fs_poll_cb(handle: *mut uv_fs_poll_t, status: c_int, prev: *const uv_stat_t, curr: *const uv_stat_t) {
+  let status = if status < 0 {
+    Err(Error::from(status))
+  } else {
+    Ok(())
+  };
+
+  let prev = Stat::from(&*prev);
+  let curr = Stat::from(&*curr);
+
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut FsPollFn| {
+    cb(&mut *(handle as *mut FsPoll), status, &prev, &curr)
+  });
+}
+
+impl FsPoll {
+  /// Initialize the handle.
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_fs_poll_init(l.as_raw(), self.as_mut_ptr())
+    };
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+    Ok(())
+  }
+
+  /// Check the file at `path` for changes every `interval` milliseconds.
+  ///
+  /// For maximum portability, use multi-second intervals. Sub-second intervals will not
+  /// detect all changes on many file systems.
+  pub fn start<P: AsRef<Path>>(&mut self, cb: FsPollCb, path: P, interval: u32) -> Result<(), Error> {
+    let path = fs::path_to_cstring(path)?;
+    let rc = unsafe {
+      uv_fs_poll_start(self.as_mut_ptr(), cb, path.as_ptr(), interval as c_uint)
+    };
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+    Ok(())
+  }
+
+  /// Same as `start()`, with a closure as callback.
+  ///
+  /// The closure receives the previous and current stat of the file. When the status is
+  /// an error, `prev` holds the last successful stat and `curr` is zeroed.
+  pub fn start_with<P, F>(&mut self, path: P, interval: u32, cb: F) -> Result<(), Error>
+  where
+    P: AsRef<Path>,
+    F: FnMut(&mut FsPoll, Result<(), Error>, &Stat, &Stat) + 'static
+  {
+    self.start(Some(fs_poll_cb), path, interval)?;
+    context::set::<FsPollFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
+  /// Stop the handle, the callback will no longer be called.
+  pub fn stop(&mut self) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_fs_poll_stop(self.as_mut_ptr())
+    };
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+    Ok(())
+  }
+
+  /// Get the path being monitored by the handle. Fails with `EINVAL` if the handle is not active.
+  pub fn getpath(&mut self) -> Result<PathBuf, Error> {
+    let handle = self.as_mut_ptr();
+    fs::getpath(|buf, size| unsafe { uv_fs_poll_getpath(handle, buf, size) })
+  }
+}
+
+impl HandleImpl for FsPoll {
+  fn as_handle(&self) -> &Handle {
+    unsafe {
+      let ptr = self.as_ptr() as *const Handle;
+      ptr.as_ref().unwrap()
+    }
+  }
+  fn as_mut_handle(&mut self) -> &mut Handle {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Handle;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+impl fmt::Debug for FsPoll {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("FsPoll")
+      .field("data",  &self.data)
+      .field("loop",  &self.loop_)
+      .field("type",  &self.type_)
+      .field("close_cb",  &self.close_cb)
+      .field("handle_queue",  &self.handle_queue)
+      .field("u",  unsafe { &self.u.fd })
+      .field("next_closing",  &self.next_closing)
+      .field("flags",  &self.flags)
+      .field("poll_ctx",  &self.poll_ctx)
+      .finish()
+  }
+}
+
+#[test]
+fn test_layout_fs_poll() {
+  use std::mem;
+  assert_eq!(
+    mem::size_of::<FsPoll>(),
+    mem::size_of::<uv_fs_poll_t>(),
+    concat!("Size of: ", stringify!(FsPoll))
+  );
+  assert_eq!(
+    mem::align_of::<FsPoll>(),
+    mem::align_of::<uv_fs_poll_t>(),
+    concat!("Alignment of ", stringify!(FsPoll))
+  );
+  assert_eq!(
+    mem::align_of::<FsPoll>(),
+    mem::align_of::<Handle>(),
+    concat!("Alignment of ", stringify!(FsPoll))
+  );
+}
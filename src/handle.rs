@@ -1,5 +1,7 @@
+use std::pin::Pin;
 use std::os::raw::c_void;
 
+use crate::context;
 use crate::error::Error;
 use crate::r#loop::Loop;
 use crate::bindings::{
@@ -13,7 +15,7 @@ pub type Fd       = uv_os_fd_t /* i32 */;
 pub type CloseCb  = uv_close_cb /* Option<unsafe extern "C" fn(handle: *mut uv_handle_t)> */;
 
 #[repr(u32)]
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(non_camel_case_types)]
 pub enum HandleType {
   UnknownHandle = /* 00 */ UV_UNKNOWN_HANDLE,
@@ -95,12 +97,12 @@ pub trait HandleImpl {
     }
   }
 
-  fn get_mut_loop(&mut self) -> &mut Loop {
+  fn get_mut_loop(&mut self) -> Pin<&mut Loop> {
     use crate::bindings::*;
     unsafe {
       let handle = self.as_mut_handle().as_mut_ptr();
       let pointer = uv_handle_get_loop(handle) as *mut Loop;
-      pointer.as_mut().unwrap()
+      Pin::new_unchecked(pointer.as_mut().unwrap())
     }
   }
 
@@ -135,7 +137,7 @@ pub trait HandleImpl {
     data
   }
 
-  fn is_active(&mut self) -> bool {
+  fn is_active(&self) -> bool {
     use crate::bindings::*;
     1 == unsafe {
       uv_is_active(self.as_handle().as_ptr())
@@ -149,11 +151,44 @@ pub trait HandleImpl {
     }
   }
 
+  /// Reference the handle: the loop will not exit while the handle is active.
+  /// References are idempotent, so calling it multiple times has no effect.
+  fn r#ref(&mut self) {
+    use crate::bindings::*;
+    unsafe {
+      uv_ref(self.as_mut_handle().as_mut_ptr())
+    }
+  }
+
+  /// Un-reference the handle: the loop may exit even if the handle is still active.
+  fn unref(&mut self) {
+    use crate::bindings::*;
+    unsafe {
+      uv_unref(self.as_mut_handle().as_mut_ptr())
+    }
+  }
+
+  /// Returns `true` if the handle is referenced.
+  fn has_ref(&self) -> bool {
+    use crate::bindings::*;
+    0 != unsafe {
+      uv_has_ref(self.as_handle().as_ptr())
+    }
+  }
+
+  /// Request handle to be closed. `close_cb` will be called asynchronously after this call.
+  /// Closures passed to the `*_with` methods are kept in the handle `data` slot until then,
+  /// and dropped right before `close_cb` is invoked.
   fn close(&mut self, close_cb: CloseCb) {
     use crate::bindings::*;
-    let handle = self.as_mut_handle();
+    let handle = self.as_mut_handle().as_mut_ptr();
     unsafe {
-      uv_close(handle.as_mut_ptr(), close_cb)
+      if context::is_owned(handle) {
+        context::set_close_cb(handle, close_cb);
+        uv_close(handle, Some(context::close_trampoline))
+      } else {
+        uv_close(handle, close_cb)
+      }
     }
   }
 
@@ -173,6 +208,75 @@ pub trait HandleImpl {
   }
 }
 
+/// Socket handles (`Tcp`, `Pipe` and `Udp`), whose operating system buffers can be sized.
+pub trait SocketImpl: HandleImpl {
+  /// Gets the size of the send buffer that the operating system uses for the socket.
+  fn send_buffer_size(&mut self) -> Result<usize, Error> {
+    use crate::bindings::*;
+
+    let mut value: c_int = 0;
+    let rc = unsafe {
+      uv_send_buffer_size(self.as_mut_handle().as_mut_ptr(), &mut value)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(value as usize)
+  }
+
+  /// Sets the size of the send buffer that the operating system uses for the socket.
+  /// Linux will set double the size and return double the size of the original set value.
+  fn set_send_buffer_size(&mut self, size: usize) -> Result<(), Error> {
+    use crate::bindings::*;
+
+    let mut value = size as c_int;
+    let rc = unsafe {
+      uv_send_buffer_size(self.as_mut_handle().as_mut_ptr(), &mut value)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Gets the size of the receive buffer that the operating system uses for the socket.
+  fn recv_buffer_size(&mut self) -> Result<usize, Error> {
+    use crate::bindings::*;
+
+    let mut value: c_int = 0;
+    let rc = unsafe {
+      uv_recv_buffer_size(self.as_mut_handle().as_mut_ptr(), &mut value)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(value as usize)
+  }
+
+  /// Sets the size of the receive buffer that the operating system uses for the socket.
+  /// Linux will set double the size and return double the size of the original set value.
+  fn set_recv_buffer_size(&mut self, size: usize) -> Result<(), Error> {
+    use crate::bindings::*;
+
+    let mut value = size as c_int;
+    let rc = unsafe {
+      uv_recv_buffer_size(self.as_mut_handle().as_mut_ptr(), &mut value)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+}
+
 impl HandleImpl for Handle {
   #[inline(always)]
   fn as_handle(&self) -> &Handle {
@@ -184,6 +288,9 @@ impl HandleImpl for Handle {
   }
 }
 
+pub mod owned;
+pub use self::owned::OwnedHandle;
+
 #[test]
 fn test_layout_handle() {
   use std::mem;
@@ -198,3 +305,28 @@ fn test_layout_handle() {
     concat!("Alignment of ", stringify!(Handle))
   );
 }
+
+#[test]
+fn test_handle_ref() {
+  use std::rc::Rc;
+  use std::cell::Cell;
+  use crate::r#loop::RunMode;
+  use crate::timer::Timer;
+
+  let l = Loop::new().unwrap();
+  let mut timer = OwnedHandle::new(&l, Timer::init).unwrap();
+  let fired = Rc::new(Cell::new(false));
+  let set = fired.clone();
+
+  timer.get_mut().start_with(60_000, 0, move |_| set.set(true)).unwrap();
+  assert!(timer.has_ref());
+
+  // An unreferenced timer does not keep the loop running.
+  timer.get_mut().unref();
+  assert!(!timer.has_ref());
+  l.run(RunMode::Default).unwrap();
+  assert!(!fired.get());
+
+  timer.get_mut().r#ref();
+  assert!(timer.has_ref() && l.alive());
+}
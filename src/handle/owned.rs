@@ -0,0 +1,140 @@
+use std::fmt;
+use std::ptr;
+use std::mem::ManuallyDrop;
+use std::pin::Pin;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use crate::context;
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::handle::{HandleImpl, HandleType};
+use crate::bindings::uv_handle_t;
+
+/// Heap allocated handle which closes itself when dropped.
+///
+/// The handle borrows its loop, so it cannot outlive it. libuv keeps pointers to the handle
+/// while it is open, so the C struct is pinned on the heap: the `OwnedHandle` derefs to `&T`,
+/// and mutable access goes through the unsafe `as_mut()`, since the handle must not be moved or
+/// overwritten. Dropping an initialized handle calls `uv_close`; the memory is released from the
+/// close callback, once libuv no longer references the handle. Handles which were never
+/// initialized are freed right away.
+///
+/// The close callback only runs while the loop is running: loops created with `Loop::new()`
+/// take care of it when dropped, other loops should be run after the last handles are dropped.
+pub struct OwnedHandle<'l, T: HandleImpl> {
+  handle: ManuallyDrop<Pin<Box<T>>>,
+  _loop:  PhantomData<&'l Loop>,
+}
+
+unsafe extern "C" fn free_cb<T>(handle: *mut uv_handle_t) {
+  drop(Box::from_raw(handle as *mut T));
+}
+
+impl<'l, T: HandleImpl + Default> OwnedHandle<'l, T> {
+  /// Allocate a handle and initialize it on `l` with `init`, which is usually the `init`
+  /// function of its type: `OwnedHandle::new(&l, Timer::init)`.
+  pub fn new<F>(l: &'l Loop, init: F) -> Result<Self, Error>
+  where
+    F: FnOnce(&mut T, &Loop) -> Result<(), Error>
+  {
+    let mut handle = Self::from_box(l, Box::<T>::default());
+    init(handle.get_mut(), l)?;
+    Ok(handle)
+  }
+}
+
+impl<'l, T: HandleImpl> OwnedHandle<'l, T> {
+  /// Take ownership of a boxed handle, which must be either uninitialized or initialized on `l`.
+  ///
+  /// # Panics
+  /// If the handle was initialized on another loop. The handle is leaked in that case, since
+  /// the other loop still references it.
+  pub fn from_box(l: &'l Loop, handle: Box<T>) -> Self {
+    let initialized = !matches!(handle.get_type(), HandleType::UnknownHandle);
+
+    if initialized && !ptr::eq(handle.get_loop(), l) {
+      Box::leak(handle);
+      panic!("handle initialized on another loop");
+    }
+
+    OwnedHandle {
+      handle: ManuallyDrop::new(Box::into_pin(handle)),
+      _loop:  PhantomData,
+    }
+  }
+
+  /// Close the handle. Same as dropping it.
+  pub fn close(self) {
+    drop(self)
+  }
+
+  /// Mutable access to the handle.
+  ///
+  /// # Safety
+  /// The handle must not be moved out of the reference or overwritten (e.g. with
+  /// `mem::swap` or `mem::replace`), since libuv keeps pointers to it until it is closed.
+  pub unsafe fn as_mut(&mut self) -> &mut T {
+    self.get_mut()
+  }
+
+  /// Mutable access for the crate, which never moves the handle.
+  pub(crate) fn get_mut(&mut self) -> &mut T {
+    unsafe { self.handle.as_mut().get_unchecked_mut() }
+  }
+
+  pub fn as_ptr(&self) -> *const T {
+    &**self.handle as *const T
+  }
+
+  pub fn as_mut_ptr(&mut self) -> *mut T {
+    self.get_mut() as *mut T
+  }
+
+  /// Give up ownership of the handle. The caller becomes responsible for closing it
+  /// and releasing the memory (e.g. with `Box::from_raw` in the close callback).
+  pub fn into_raw(self) -> *mut T {
+    let mut this = ManuallyDrop::new(self);
+    let handle = unsafe { ManuallyDrop::take(&mut this.handle) };
+    Box::into_raw(unsafe { Pin::into_inner_unchecked(handle) })
+  }
+}
+
+impl<T: HandleImpl> Deref for OwnedHandle<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.handle
+  }
+}
+
+impl<T: HandleImpl> Drop for OwnedHandle<'_, T> {
+  fn drop(&mut self) {
+    let ptr = unsafe { Pin::into_inner_unchecked(ManuallyDrop::take(&mut self.handle)) };
+    let ptr = Box::into_raw(ptr);
+    let handle = unsafe { &mut *ptr };
+
+    match handle.get_type() {
+      HandleType::UnknownHandle => unsafe {
+        drop(Box::from_raw(ptr))
+      },
+      // Closed through `as_mut()`: released after its close callback if the close went
+      // through the context of the handle, leaked otherwise.
+      _ if handle.is_closing() => unsafe {
+        if context::is_owned(ptr as *const uv_handle_t) {
+          context::set_free_cb(ptr as *mut uv_handle_t, Some(free_cb::<T>));
+        }
+      },
+      _ => handle.close(Some(free_cb::<T>)),
+    }
+  }
+}
+
+impl<T: HandleImpl + fmt::Debug> fmt::Debug for OwnedHandle<'_, T> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_tuple("OwnedHandle")
+      .field(self.deref())
+      .finish()
+  }
+}
@@ -1,24 +1,33 @@
 use std::fmt;
 
 use crate::bindings::{
-  uv_idle_t, uv_idle_cb,
+  uv_idle_t, uv_idle_cb, uv_handle_t,
   uv_idle_init, uv_idle_start, uv_idle_stop
 };
 
+use crate::context::{self, Slot};
 use crate::r#loop::Loop;
 use crate::error::Error;
 use crate::handle::{Handle, HandleImpl};
 
 pub type IdleCb = uv_idle_cb;
 
+type IdleFn = Box<dyn FnMut(&mut Idle)>;
+
 repr!{
   pub type Idle = uv_idle_t;
 }
 
+unsafe extern "C" fn idle_cb(handle: *mut uv_idle_t) {
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut IdleFn| {
+    cb(&mut *(handle as *mut Idle))
+  });
+}
+
 impl Idle {
-  pub fn init(&mut self, l: &mut Loop) -> Result<(), Error> {
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
     let rc = unsafe {
-      uv_idle_init(l.as_mut_ptr(), self.as_mut_ptr())
+      uv_idle_init(l.as_raw(), self.as_mut_ptr())
     };
     if rc < 0 {
       return Err(Error::from(rc));
@@ -36,6 +45,16 @@ impl Idle {
     Ok(())
   }
 
+  /// Same as `start()`, with a closure as callback.
+  pub fn start_with<F>(&mut self, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Idle) + 'static
+  {
+    self.start(Some(idle_cb))?;
+    context::set::<IdleFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
   pub fn stop(&mut self) -> Result<(), Error> {
     let rc = unsafe {
       uv_idle_stop(self.as_mut_ptr())
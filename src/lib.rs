@@ -16,25 +16,35 @@ pub use self::r#loop::{
   Loop, RunMode, LoopOption
 };
 
+pub mod net;
+
 pub mod buf;
 pub use self::buf::Buf;
 
+mod context;
+
 pub mod handle;
 pub use self::handle::{
-  Handle, HandleType, HandleImpl, CloseCb
+  Handle, HandleType, HandleImpl, SocketImpl, CloseCb, OwnedHandle
 };
 
 pub mod req;
 pub use self::req::{
   Req, ReqType, ReqImpl,
-  Write, Connect, Shutdown, 
-  WriteCb, ConnectCb, ShutdownCb
+  Write, Connect, Shutdown, UdpSend, Fs, Work, GetAddrInfo, GetNameInfo, Random,
+  WriteCb, ConnectCb, ShutdownCb, UdpSendCb, FsCb, WorkCb, AfterWorkCb,
+  GetAddrInfoCb, GetNameInfoCb, RandomCb
 };
 
 pub mod stream;
 pub use self::stream::{
-  Stream, StreamImpl, AllocCb, ReadCb, ConnectionCb
+  Stream, StreamImpl, AllocCb, ReadCb, ConnectionCb,
+  ReadAlloc, BufferPool, SlabBuffer, RingBuffer
 };
+#[cfg(feature = "bytes")]
+pub use self::stream::BytesBuffer;
+#[cfg(feature = "futures-io")]
+pub use self::stream::AsyncStream;
 
 pub mod poll;
 pub use self::poll::{Poll, PollEvent, PollCb};
@@ -48,6 +58,9 @@ pub use self::process::{Process, ProcessOptions};
 pub mod signal;
 pub use self::signal::{Signal, SignalCb};
 
+pub mod r#async;
+pub use self::r#async::{Async, AsyncCb, AsyncSender};
+
 pub mod idle;
 pub use self::idle::{Idle, IdleCb};
 
@@ -61,8 +74,48 @@ pub mod timer;
 pub use self::timer::Timer;
 
 pub mod pipe;
-pub use self::pipe::Pipe;
+pub use self::pipe::{Pipe, PendingHandle};
 
 pub mod tcp;
-pub use self::tcp::Tcp;
+pub use self::tcp::{Tcp, TcpListener, TcpStream};
+
+pub mod tty;
+pub use self::tty::{Tty, TtyMode, VtermState};
+
+pub mod udp;
+pub use self::udp::{Udp, UdpFlags, UdpRecvCb, Membership};
+
+pub mod fs;
+pub use self::fs::{
+  Dir, Stat, StatFs, DirEntry, DirentType,
+  OpenFlags, CopyFlags, SymlinkFlags
+};
+
+pub mod fs_event;
+pub use self::fs_event::{FsEvent, FsEventCb, FsEventKind, FsEventFlags};
+
+pub mod fs_poll;
+pub use self::fs_poll::{FsPoll, FsPollCb};
+
+pub mod work;
+pub use self::work::QueuedWork;
+
+pub mod dns;
+pub use self::dns::{
+  AddrInfo, AddrInfoHints, AddrInfoFlags, NameInfoFlags,
+  Family, SockType, Protocol
+};
+
+pub mod random;
+
+pub mod walk;
+pub use self::walk::{HandleRef, HandleInfo};
+
+pub mod metrics;
+pub use self::metrics::{Metrics, Utilization, EluSampler};
+
+pub mod executor;
+pub use self::executor::JoinHandle;
 
+#[cfg(all(unix, feature = "tokio"))]
+pub mod tokio_bridge;
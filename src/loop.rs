@@ -1,35 +1,45 @@
 use std::fmt;
-use std::os::raw::c_void;
+use std::any::Any;
+use std::panic;
+use std::pin::Pin;
+use std::marker::PhantomPinned;
+use std::cell::{RefCell, UnsafeCell};
+use std::sync::{Mutex, MutexGuard};
+use std::collections::BTreeSet;
+use std::os::raw::{c_int, c_void};
 use crate::error::Error;
+use crate::handle::{Handle, HandleImpl};
+use crate::walk::HandleInfo;
 use crate::bindings::{
-  self, uv_loop_t, uv_loop_option, uv_run_mode,
+  self, uv_loop_t, uv_loop_option, uv_run_mode, uv_handle_t,
   UV_LOOP_BLOCK_SIGNAL, UV_METRICS_IDLE_TIME,
   UV_RUN_DEFAULT, UV_RUN_ONCE, UV_RUN_NOWAIT,
 };
 
-repr!{
-  pub type Loop = uv_loop_t;
-}
+/// Event loop. libuv updates the loop from most of the calls taking it, including the ones
+/// which only need a shared reference (`run`, `stop`, handle initialization, ...), so the
+/// C struct lives in an `UnsafeCell`. An initialized loop points to itself, so it is `!Unpin`
+/// and only handed out pinned or by reference.
+#[repr(transparent)]
+pub struct Loop(UnsafeCell<uv_loop_t>, PhantomPinned);
 
 pub type WalkCb = bindings::uv_walk_cb /* Option<unsafe extern "C" fn(*mut uv_handle_t, *mut c_void)> */;
 
-#[repr(u32)]
 #[derive(Debug, Copy, Clone)]
 pub enum LoopOption {
-  /// Block a signal when polling for new events.
-  /// The second argument to `uv_loop_configure` is the signal number.
+  /// Block a signal when polling for new events. The argument is the signal number.
   /// This operation is currently only implemented for SIGPROF signals, to suppress unnecessary
-  /// wakeups when using a sampling profiler. Reqing other signals will fail with UV_EINVAL.
-  LoopBlockSignal = UV_LOOP_BLOCK_SIGNAL,
+  /// wakeups when using a sampling profiler. Requesting other signals will fail with UV_EINVAL.
+  LoopBlockSignal(c_int),
   /// Accumulate the amount of idle time the event loop spends in the event provider.
   /// This option is necessary to use `uv_metrics_idle_time`.
-  MetricsIdleTime = UV_METRICS_IDLE_TIME,
+  MetricsIdleTime,
 }
 
 impl From<LoopOption> for uv_loop_option {
   fn from(option: LoopOption) -> uv_loop_option {
     match option {
-      LoopOption::LoopBlockSignal => UV_LOOP_BLOCK_SIGNAL,
+      LoopOption::LoopBlockSignal(_) => UV_LOOP_BLOCK_SIGNAL,
       LoopOption::MetricsIdleTime => UV_METRICS_IDLE_TIME,
     }
   }
@@ -62,21 +72,103 @@ impl From<RunMode> for uv_run_mode {
   }
 }
 
+thread_local! {
+  /// Panic caught in a callback, waiting to be resumed by `Loop::run` on this thread.
+  static PANIC: RefCell<Option<Box<dyn Any + Send>>> = RefCell::new(None);
+}
+
+/// Keep the payload of a panic caught in a callback and stop the loop, so that `Loop::run`
+/// resumes it once control is back in Rust. Only the first panic is kept.
+pub(crate) fn set_panic(l: *mut uv_loop_t, payload: Box<dyn Any + Send>) {
+  PANIC.with(|panic| {
+    panic.borrow_mut().get_or_insert(payload);
+  });
+
+  unsafe {
+    bindings::uv_stop(l)
+  }
+}
+
+/// Addresses of the loops allocated by `Loop::new()` which have not been closed yet.
+static OWNED: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+fn owned() -> MutexGuard<'static, BTreeSet<usize>> {
+  OWNED.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Handles allocated by the crate are released by `context::close_trampoline`.
+unsafe extern "C" fn close_walk_cb(handle: *mut uv_handle_t, _arg: *mut c_void) {
+  let handle = &mut *(handle as *mut Handle);
+
+  if !handle.is_closing() {
+    handle.close(None);
+  }
+}
+
+/// Return the current high-resolution real time, in nanoseconds.
+/// It is relative to an arbitrary time in the past and not subject to clock drift.
+pub fn hrtime() -> u64 {
+  unsafe {
+    bindings::uv_hrtime()
+  }
+}
+
 impl Loop {
-  pub fn get_default() -> &'static mut Self {
+  /// Allocate and initialize a new event loop.
+  ///
+  /// The loop closes itself when dropped. Handles still open at that point are closed, and
+  /// the loop runs until the close callbacks and pending requests complete, since libuv may
+  /// still use it until then (e.g. from the threadpool). Use `close()` to get the loop back
+  /// along with its open handles instead.
+  pub fn new() -> Result<Pin<Box<Loop>>, Error> {
+    let mut l = Box::new(Loop(UnsafeCell::new(uv_loop_t::default()), PhantomPinned));
+
+    let rc = unsafe {
+      bindings::uv_loop_init(l.as_mut_ptr())
+    };
+
+    if rc < 0 {
+      return Err( Error::from(rc) );
+    }
+
+    owned().insert(l.as_ptr() as usize);
+    Ok(Box::into_pin(l))
+  }
+
+  /// Returns the default loop, which is never closed.
+  pub fn get_default() -> &'static Self {
     unsafe {
-      let ptr = crate::bindings::uv_default_loop() as *mut Loop;
-      ptr.as_mut().unwrap()
+      let ptr = crate::bindings::uv_default_loop() as *const Loop;
+      ptr.as_ref().unwrap()
     }
   }
 
-  pub fn init(&mut self) -> Result<(), Error> {
+  pub fn as_ptr(&self) -> *const uv_loop_t {
+    self.0.get()
+  }
+
+  pub fn as_mut_ptr(&mut self) -> *mut uv_loop_t {
+    self.0.get_mut()
+  }
+
+  /// Pointer passed to the libuv functions which update the loop through a shared reference.
+  pub(crate) fn as_raw(&self) -> *mut uv_loop_t {
+    self.0.get()
+  }
+
+  /// Runs the event loop. If a callback panicked (e.g. a `queue_work()` closure), the loop
+  /// is stopped and the panic is resumed from here.
+  pub fn run(&self, mode: RunMode) -> Result<(), Error> {
     use crate::bindings::*;
 
     let rc = unsafe {
-      uv_loop_init( self.as_mut_ptr() )
+      uv_run(self.as_raw(), mode.into())
     };
 
+    if let Some(payload) = PANIC.with(|panic| panic.borrow_mut().take()) {
+      panic::resume_unwind(payload);
+    }
+
     if rc < 0 {
       return Err( Error::from(rc) );
     }
@@ -84,32 +176,49 @@ impl Loop {
     Ok(())
   }
 
-  pub fn run(&mut self, mode: RunMode) -> Result<(), Error> {
+  pub fn stop(&self) {
+    use crate::bindings::*;
+    unsafe {
+      uv_stop( self.as_raw() )
+    }
+  }
+
+  /// Releases all internal loop resources.
+  /// Fails while handles are open or requests are pending, giving back the loop along with
+  /// its handles (empty if only requests are pending).
+  pub fn close(self: Pin<Box<Self>>) -> Result<(), (Pin<Box<Self>>, Vec<HandleInfo>)> {
     use crate::bindings::*;
 
     let rc = unsafe {
-      uv_run(self.as_mut_ptr(), mode.into())
+      uv_loop_close( self.as_raw() )
     };
 
     if rc < 0 {
-      return Err( Error::from(rc) );
+      let handles = self.handles();
+      return Err((self, handles));
     }
 
+    owned().remove(&(self.as_ptr() as usize));
     Ok(())
   }
 
-  pub fn stop(&mut self) {
+  pub fn alive(&self) -> bool {
     use crate::bindings::*;
     unsafe {
-      uv_stop( self.as_mut_ptr() )
+      uv_loop_alive(self.as_ptr()) != 0
     }
   }
 
-  pub fn close(&mut self) -> Result<(), Error> {
+  /// Set additional loop options.
+  /// You should normally call this before the first call to `run()` unless mentioned otherwise.
+  pub fn configure(&self, option: LoopOption) -> Result<(), Error> {
     use crate::bindings::*;
 
     let rc = unsafe {
-      uv_loop_close( self.as_mut_ptr() )
+      match option {
+        LoopOption::LoopBlockSignal(signum) => uv_loop_configure(self.as_raw(), option.into(), signum),
+        LoopOption::MetricsIdleTime => uv_loop_configure(self.as_raw(), option.into()),
+      }
     };
 
     if rc < 0 {
@@ -119,13 +228,49 @@ impl Loop {
     Ok(())
   }
 
-  pub fn alive(&self) -> bool {
+  /// Get the backend file descriptor. Only kqueue, epoll and event ports are supported.
+  ///
+  /// This can be used in conjunction with `run(RunMode::NoWait)` to poll in one thread and
+  /// run the event loop’s callbacks in another.
+  pub fn backend_fd(&self) -> Option<c_int> {
+    use crate::bindings::*;
+
+    let fd = unsafe {
+      uv_backend_fd(self.as_ptr())
+    };
+
+    if fd < 0 {
+      return None;
+    }
+
+    Some(fd)
+  }
+
+  /// Get the poll timeout in milliseconds, or `None` for no timeout.
+  pub fn backend_timeout(&self) -> Option<u64> {
+    use crate::bindings::*;
+
+    let timeout = unsafe {
+      uv_backend_timeout(self.as_ptr())
+    };
+
+    if timeout < 0 {
+      return None;
+    }
+
+    Some(timeout as u64)
+  }
+
+  /// Update the event loop’s concept of “now”. libuv caches the current time at the start of
+  /// the event loop tick in order to reduce the number of time-related system calls.
+  pub fn update_time(&self) {
     use crate::bindings::*;
     unsafe {
-      uv_loop_alive(self.as_ptr()) != 0
+      uv_update_time(self.as_raw())
     }
   }
 
+  /// Return the current timestamp in milliseconds, cached at the start of the loop tick.
   pub fn now(&self) -> u64 {
     use crate::bindings::*;
     unsafe {
@@ -140,52 +285,81 @@ impl Loop {
     }
   }
 
-  pub fn set_data<T>(&mut self, data: *mut T) {
+  pub fn set_data<T>(&self, data: *mut T) {
     use crate::bindings::*;
     unsafe {
-      uv_loop_set_data(self.as_mut_ptr(), data as *mut c_void)
+      uv_loop_set_data(self.as_raw(), data as *mut c_void)
     }
   }  
 
   /// Walk the list of handles
-  /// `walk_cb` will be executed with the given `ctx`.
+  /// `walk_cb` will be executed with the given `ctx`. See `walk()` for a closure-based variant.
   #[allow(clippy::not_unsafe_ptr_arg_deref)]
-  pub fn walk(&mut self, walk_cb: WalkCb, ctx: *mut c_void) {
+  pub fn walk_raw(&self, walk_cb: WalkCb, ctx: *mut c_void) {
+    use crate::bindings::*;
+    unsafe {
+      uv_walk(self.as_raw(), walk_cb, ctx);
+    }
+  }
+}
+
+impl Drop for Loop {
+  fn drop(&mut self) {
     use crate::bindings::*;
+
+    if !owned().remove(&(self.as_ptr() as usize)) {
+      return;
+    }
+
+    // Freeing the loop while libuv still uses it would be a use after free, so it is run
+    // until it can be closed, closing again the handles opened by the callbacks.
     unsafe {
-      uv_walk(self.as_mut_ptr(), walk_cb, ctx);
+      let l = self.as_raw();
+
+      while uv_loop_close(l) == UV_EBUSY {
+        uv_walk(l, Some(close_walk_cb), std::ptr::null_mut());
+        uv_run(l, UV_RUN_DEFAULT);
+      }
+    }
+
+    if let Some(payload) = PANIC.with(|panic| panic.borrow_mut().take()) {
+      if !std::thread::panicking() {
+        panic::resume_unwind(payload);
+      }
     }
   }
 }
 
 impl fmt::Debug for Loop {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let l = unsafe { &*self.as_ptr() };
+
     f.debug_struct("Loop")
-      .field("active_handles", &self.active_handles)
-      .field("handle_queue", &self.handle_queue)
-      .field("active_reqs", unsafe { &self.active_reqs.count })
-      .field("internal_fields", &self.internal_fields)
-      .field("stop_flag", &self.stop_flag)
-      .field("flags", &self.flags)
-      .field("backend_fd", &self.backend_fd)
-      .field("pending_queue", &self.pending_queue)
-      .field("watcher_queue", &self.watcher_queue)
-      .field("nwatchers", &self.nwatchers)
-      .field("nfds", &self.nfds)
-      .field("wq", &self.wq)
-      .field("closing_handles", &self.closing_handles)
-      .field("process_handles", &self.process_handles)
-      .field("prepare_handles", &self.prepare_handles)
-      .field("check_handles", &self.check_handles)
-      .field("idle_handles", &self.idle_handles)
-      .field("async_handles", &self.async_handles)
-      .field("async_unused", &self.async_unused)
-      .field("async_wfd", &self.async_wfd)
-      .field("timer_heap", &self.timer_heap)
-      .field("timer_counter", &self.timer_counter)
-      .field("time", &self.time)
-      .field("signal_pipefd", &self.signal_pipefd)
-      .field("signal_io_watcher", &self.signal_io_watcher)
+      .field("active_handles", &l.active_handles)
+      .field("handle_queue", &l.handle_queue)
+      .field("active_reqs", unsafe { &l.active_reqs.count })
+      .field("internal_fields", &l.internal_fields)
+      .field("stop_flag", &l.stop_flag)
+      .field("flags", &l.flags)
+      .field("backend_fd", &l.backend_fd)
+      .field("pending_queue", &l.pending_queue)
+      .field("watcher_queue", &l.watcher_queue)
+      .field("nwatchers", &l.nwatchers)
+      .field("nfds", &l.nfds)
+      .field("wq", &l.wq)
+      .field("closing_handles", &l.closing_handles)
+      .field("process_handles", &l.process_handles)
+      .field("prepare_handles", &l.prepare_handles)
+      .field("check_handles", &l.check_handles)
+      .field("idle_handles", &l.idle_handles)
+      .field("async_handles", &l.async_handles)
+      .field("async_unused", &l.async_unused)
+      .field("async_wfd", &l.async_wfd)
+      .field("timer_heap", &l.timer_heap)
+      .field("timer_counter", &l.timer_counter)
+      .field("time", &l.time)
+      .field("signal_pipefd", &l.signal_pipefd)
+      .field("signal_io_watcher", &l.signal_io_watcher)
       .finish()
   }
 }
@@ -211,6 +385,47 @@ fn test_layout_loop() {
   );
 }    
 
+#[test]
+fn test_loop_close() {
+  use std::rc::Rc;
+  use std::cell::Cell;
+  use std::time::Duration;
+  use crate::timer::Timer;
+  use crate::handle::HandleType;
+
+  let l = Loop::new().unwrap();
+  let mut timer = Timer::boxed();
+  timer.init(&l).unwrap();
+
+  let l = match l.close() {
+    Err((l, handles)) => {
+      assert_eq!(handles.len(), 1);
+      assert_eq!(handles[0].r#type, HandleType::Timer);
+      l
+    },
+    Ok(()) => panic!("closed a loop with an open handle"),
+  };
+
+  timer.close(None);
+  l.run(RunMode::Default).unwrap();
+  l.close().unwrap();
+
+  // Dropping the loop waits for the work still queued on the threadpool.
+  let l = Loop::new().unwrap();
+  let done = Rc::new(Cell::new(0));
+
+  for _ in 0..32 {
+    let done = done.clone();
+    l.queue_work(|| std::thread::sleep(Duration::from_millis(1)), move |res| {
+      res.unwrap();
+      done.set(done.get() + 1);
+    }).unwrap();
+  }
+
+  drop(l);
+  assert_eq!(done.get(), 32);
+}
+
 /*
 
 #[repr(C)]
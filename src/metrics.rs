@@ -0,0 +1,132 @@
+use std::fmt;
+
+use crate::r#loop::{self, Loop, LoopOption};
+use crate::error::Error;
+use crate::timer::Timer;
+use crate::handle::{HandleImpl, OwnedHandle};
+use crate::bindings::{uv_metrics_t, uv_metrics_info, uv_metrics_idle_time};
+
+/// Counters of the event loop, as reported by `uv_metrics_info`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Metrics {
+  /// Number of event loop iterations.
+  pub loop_count:     u64,
+  /// Number of events that have been processed by the event handler.
+  pub events:         u64,
+  /// Number of events that were waiting to be processed when the event provider was called.
+  pub events_waiting: u64,
+}
+
+impl From<uv_metrics_t> for Metrics {
+  fn from(metrics: uv_metrics_t) -> Self {
+    Metrics {
+      loop_count:     metrics.loop_count,
+      events:         metrics.events,
+      events_waiting: metrics.events_waiting,
+    }
+  }
+}
+
+/// Event loop utilization (ELU) over a sampling interval.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Utilization {
+  /// Time spent idle in the event provider, in nanoseconds.
+  pub idle:        u64,
+  /// Time spent running, in nanoseconds.
+  pub active:      u64,
+  /// Ratio of `active` to the whole interval, between `0.0` and `1.0`.
+  pub utilization: f64,
+}
+
+impl Utilization {
+  fn between(start: (u64, u64), end: (u64, u64)) -> Self {
+    let elapsed = end.0.saturating_sub(start.0);
+    let idle = end.1.saturating_sub(start.1).min(elapsed);
+    let active = elapsed - idle;
+
+    Utilization {
+      idle,
+      active,
+      utilization: if elapsed == 0 { 0.0 } else { active as f64 / elapsed as f64 },
+    }
+  }
+}
+
+impl Loop {
+  /// Get the counters of the event loop.
+  pub fn metrics(&self) -> Result<Metrics, Error> {
+    let mut metrics = uv_metrics_t::default();
+
+    let rc = unsafe {
+      uv_metrics_info(self.as_raw(), &mut metrics)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(Metrics::from(metrics))
+  }
+
+  /// Get the amount of time the event loop has been idle in the kernel’s event provider,
+  /// in nanoseconds. Always `0` unless the loop was configured with `LoopOption::MetricsIdleTime`.
+  pub fn metrics_idle_time(&self) -> u64 {
+    unsafe {
+      uv_metrics_idle_time(self.as_raw())
+    }
+  }
+
+  /// (hrtime, idle time) pair used to compute the utilization between two points in time.
+  fn utilization_mark(&self) -> (u64, u64) {
+    (r#loop::hrtime(), self.metrics_idle_time())
+  }
+}
+
+/// Reports the utilization of a loop every `interval`, until dropped.
+///
+/// The sampler enables `LoopOption::MetricsIdleTime` on the loop. Its timer is unreferenced,
+/// so it does not keep the loop alive.
+pub struct EluSampler<'l> {
+  /// Closed, and so stopped, when the sampler is dropped.
+  _timer:   OwnedHandle<'l, Timer>,
+  interval: u64,
+}
+
+impl<'l> EluSampler<'l> {
+  /// Start sampling `l` every `interval` milliseconds, passing each sample to `cb`.
+  pub fn start<F>(l: &'l Loop, interval: u64, mut cb: F) -> Result<Self, Error>
+  where
+    F: FnMut(Utilization) + 'static
+  {
+    l.configure(LoopOption::MetricsIdleTime)?;
+
+    let mut timer = OwnedHandle::new(l, Timer::init)?;
+    let mut last = l.utilization_mark();
+
+    timer.get_mut().start_with(interval, interval, move |timer: &mut Timer| {
+      let mark = timer.get_loop().utilization_mark();
+      cb(Utilization::between(last, mark));
+      last = mark;
+    })?;
+
+    timer.get_mut().unref();
+    Ok(EluSampler { _timer: timer, interval })
+  }
+}
+
+impl fmt::Debug for EluSampler<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("EluSampler")
+      .field("interval", &self.interval)
+      .finish()
+  }
+}
+
+#[test]
+fn test_utilization_between() {
+  let sample = Utilization::between((1_000, 100), (2_000, 350));
+  assert_eq!(sample.idle, 250);
+  assert_eq!(sample.active, 750);
+  assert_eq!(sample.utilization, 0.75);
+}
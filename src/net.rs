@@ -0,0 +1,82 @@
+use std::mem;
+use std::os::raw::c_int;
+use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6, Ipv4Addr, Ipv6Addr};
+
+use crate::bindings::{sockaddr, sockaddr_storage};
+
+/// Convert a `SocketAddr` into a `sockaddr_storage` suitable for libuv,
+/// along with the length of the underlying `sockaddr_in` / `sockaddr_in6`.
+pub fn to_sockaddr(addr: &SocketAddr) -> (sockaddr_storage, c_int) {
+  let mut storage = sockaddr_storage::default();
+
+  let len = match addr {
+    SocketAddr::V4(v4) => unsafe {
+      let sin = &mut *(&mut storage as *mut sockaddr_storage as *mut libc::sockaddr_in);
+      sin.sin_family = libc::AF_INET as libc::sa_family_t;
+      sin.sin_port = v4.port().to_be();
+      sin.sin_addr = libc::in_addr {
+        s_addr: u32::from_ne_bytes(v4.ip().octets())
+      };
+      mem::size_of::<libc::sockaddr_in>()
+    },
+    SocketAddr::V6(v6) => unsafe {
+      let sin6 = &mut *(&mut storage as *mut sockaddr_storage as *mut libc::sockaddr_in6);
+      sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+      sin6.sin6_port = v6.port().to_be();
+      sin6.sin6_flowinfo = v6.flowinfo().to_be();
+      sin6.sin6_addr = libc::in6_addr {
+        s6_addr: v6.ip().octets()
+      };
+      sin6.sin6_scope_id = v6.scope_id();
+      mem::size_of::<libc::sockaddr_in6>()
+    },
+  };
+
+  (storage, len as c_int)
+}
+
+/// Convert a `sockaddr` filled by libuv back into a `SocketAddr`.
+/// Returns `None` for null pointers and for address families other than `AF_INET` / `AF_INET6`.
+///
+/// # Safety
+/// `addr` must be null or point to a valid `sockaddr_in` / `sockaddr_in6`.
+pub unsafe fn from_sockaddr(addr: *const sockaddr) -> Option<SocketAddr> {
+  if addr.is_null() {
+    return None;
+  }
+
+  let family = (*(addr as *const libc::sockaddr)).sa_family as c_int;
+
+  match family {
+    libc::AF_INET => {
+      let sin = &*(addr as *const libc::sockaddr_in);
+      let ip = Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes());
+      Some(SocketAddr::V4(SocketAddrV4::new(ip, u16::from_be(sin.sin_port))))
+    },
+    libc::AF_INET6 => {
+      let sin6 = &*(addr as *const libc::sockaddr_in6);
+      let ip = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+      Some(SocketAddr::V6(SocketAddrV6::new(
+        ip,
+        u16::from_be(sin6.sin6_port),
+        u32::from_be(sin6.sin6_flowinfo),
+        sin6.sin6_scope_id
+      )))
+    },
+    _ => None
+  }
+}
+
+#[test]
+fn test_sockaddr_roundtrip() {
+  let addrs: [SocketAddr; 2] = [
+    "127.0.0.1:8080".parse().unwrap(),
+    "[::1]:5353".parse().unwrap(),
+  ];
+
+  for addr in addrs.iter() {
+    let (storage, _) = to_sockaddr(addr);
+    let ptr = &storage as *const sockaddr_storage as *const sockaddr;
+    assert_eq!(unsafe { from_sockaddr(ptr) }, Some(*addr));
+  }
+}
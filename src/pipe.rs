@@ -1,18 +1,24 @@
 use std::fmt;
+use std::ptr;
+use std::ffi::CString;
 use std::convert::{From, Into};
-use std::os::raw::{c_int, c_char};
+use std::os::raw::{c_int, c_uint, c_char, c_void};
 
 use crate::{HandleType, ReqImpl};
 
+use crate::buf::Buf;
 use crate::r#loop::Loop;
 use crate::error::Error;
 use crate::poll::PollEvent;
-use crate::req::{Connect, ConnectCb};
+use crate::tcp::Tcp;
+use crate::udp::Udp;
+use crate::req::{Connect, ConnectCb, Write};
+use crate::req::owned::{OwnedReq, Completion, RequestFn};
 use crate::stream::{Stream, StreamImpl};
-use crate::handle::{Handle, HandleImpl};
+use crate::handle::{Handle, HandleImpl, SocketImpl, OwnedHandle};
 use crate::bindings::{
-  uv_pipe_t, uv_poll_event, size_t, uv_file,
-  uv_pipe_init, uv_pipe_connect,
+  uv_pipe_t, uv_poll_event, size_t, uv_file, uv_stream_t,
+  uv_pipe_init, uv_pipe_connect, uv_write2, uv_accept,
   uv_pipe_getsockname, uv_pipe_getpeername,
   uv_pipe_bind, uv_pipe_pending_type, uv_pipe_chmod,
   uv_pipe_pending_count, uv_pipe_open,
@@ -40,6 +46,8 @@ impl HandleImpl for Pipe {
   }
 }
 
+impl SocketImpl for Pipe {}
+
 impl StreamImpl for Pipe {
   fn as_stream(&self) -> &Stream {
     unsafe {
@@ -61,9 +69,9 @@ impl Pipe {
   /// for handle passing between processes (which may change the bytes on the wire). 
   /// Only a connected pipe that will be passing the handles should have this flag set, 
   /// not the listening pipe that uv_accept is called on.
-  pub fn init(&mut self, l: &mut Loop, ipc: bool) -> Result<(), Error> {
+  pub fn init(&mut self, l: &Loop, ipc: bool) -> Result<(), Error> {
     let rc = unsafe {
-      uv_pipe_init(l.as_mut_ptr(), self as *mut Pipe, if ipc { 1 } else { 0 })
+      uv_pipe_init(l.as_raw(), self as *mut Pipe, if ipc { 1 } else { 0 })
     };
 
     if rc < 0 {
@@ -105,21 +113,32 @@ impl Pipe {
   }
 
   /// Connect to the Unix domain socket or the named pipe.
+  /// The `data` field of the request points to the pipe. The request is freed once `cb` returns.
   pub fn connect(&mut self, name: &str, cb: ConnectCb) -> Result<(), Error> {
-    let mut req = Connect::boxed();
-    req.set_data(self as *mut Self as *mut libc::c_void);
+    let name = CString::new(name).map_err(|_| Error::EINVAL)?;
+    let req = OwnedReq::into_raw(Completion::Raw(cb), None);
 
-    let req_ptr = req.as_mut_ptr();
+    unsafe {
+      (*req).set_data(self as *mut Self as *mut c_void);
+      uv_pipe_connect(req, self as *mut Pipe, name.as_ptr(), Some(OwnedReq::<Connect>::trampoline))
+    };
 
-    let _ = Box::into_raw(req);
+    Ok(())
+  }
 
-    let mut buf = String::with_capacity(name.len() + 1);
-    buf.push_str(name);
-    buf.push('\0');
+  /// Same as `connect()`, with a closure called once the connection is established or failed.
+  pub fn connect_with<F>(&mut self, name: &str, cb: F) -> Result<(), Error>
+  where
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    let name = CString::new(name).map_err(|_| Error::EINVAL)?;
+
+    let cb: RequestFn = Box::new(cb);
+    let req = OwnedReq::into_raw(Completion::from(cb), None);
 
     unsafe {
-      uv_pipe_connect(req_ptr, self as *mut Pipe, buf.as_mut_ptr() as *const c_char, cb)
-    };
+      uv_pipe_connect(req, self as *mut Pipe, name.as_ptr(), Some(OwnedReq::<Connect>::trampoline));
+    }
 
     Ok(())
   }
@@ -218,6 +237,103 @@ impl Pipe {
     
     Ok( HandleType::from(ty) )
   }
+
+  /// Send `handle` along with `data` over an IPC pipe, e.g. to hand an accepted connection
+  /// to another process. `handle` must be a TCP socket or a pipe which is a server or a
+  /// connection; it can be closed once `cb` is called. `data` must not be empty and is kept
+  /// alive by the request until it completes.
+  pub fn send_handle<S, B, F>(&mut self, handle: &mut S, data: B, cb: F) -> Result<(), Error>
+  where
+    S: StreamImpl,
+    B: Into<Vec<u8>>,
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    let data: Vec<u8> = data.into();
+
+    // The handle travels as ancillary data of the first bytes written.
+    if data.is_empty() {
+      return Err(Error::EINVAL);
+    }
+
+    let bufs = [Buf::from(&data[..])];
+    let cb: RequestFn = Box::new(cb);
+    let req = OwnedReq::into_raw(Completion::from(cb), Some(Box::new(data)));
+
+    let rc = unsafe {
+      uv_write2(
+        req, self.as_mut_stream().as_mut_ptr(), bufs.as_ptr(), bufs.len() as c_uint,
+        handle.as_mut_stream().as_mut_ptr(), Some(OwnedReq::<Write>::trampoline)
+      )
+    };
+
+    if rc < 0 {
+      unsafe { OwnedReq::abort(req) };
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Accept the next handle received over an IPC pipe, initialized on `l` according to
+  /// `pending_type()`. Handles arrive along with the data read from the pipe, so this is
+  /// usually called from the read callback while `pending_count()` is not zero.
+  /// Fails with `EAGAIN` if no handle is pending, and with `EINVAL` if `l` is not the loop of
+  /// the pipe.
+  pub fn accept_pending<'l>(&mut self, l: &'l Loop) -> Result<PendingHandle<'l>, Error> {
+    fn accept<'l, T: HandleImpl>(server: &mut Pipe, mut client: OwnedHandle<'l, T>) -> Result<OwnedHandle<'l, T>, Error> {
+      let rc = unsafe {
+        uv_accept(server.as_mut_stream().as_mut_ptr(), client.as_mut_ptr() as *mut uv_stream_t)
+      };
+
+      if rc < 0 {
+        return Err(Error::from(rc));
+      }
+
+      Ok(client)
+    }
+
+    if !ptr::eq(l, self.get_loop()) {
+      return Err(Error::EINVAL);
+    }
+
+    match self.pending_type()? {
+      HandleType::Tcp => {
+        Ok(PendingHandle::Tcp(accept(self, OwnedHandle::new(l, Tcp::init)?)?))
+      },
+      HandleType::NamedPipe => {
+        let pipe = OwnedHandle::new(l, |pipe: &mut Pipe, l| pipe.init(l, false))?;
+        Ok(PendingHandle::Pipe(accept(self, pipe)?))
+      },
+      HandleType::Udp => {
+        Ok(PendingHandle::Udp(accept(self, OwnedHandle::new(l, Udp::init)?)?))
+      },
+      HandleType::UnknownHandle => Err(Error::EAGAIN),
+      _ => Err(Error::ENOTSUP),
+    }
+  }
+}
+
+/// Handle received over an IPC pipe, returned by `Pipe::accept_pending()`.
+pub enum PendingHandle<'l> {
+  Tcp(OwnedHandle<'l, Tcp>),
+  Pipe(OwnedHandle<'l, Pipe>),
+  Udp(OwnedHandle<'l, Udp>),
+}
+
+impl fmt::Debug for PendingHandle<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let handle = match self {
+      PendingHandle::Tcp(tcp)   => tcp.as_handle(),
+      PendingHandle::Pipe(pipe) => pipe.as_handle(),
+      PendingHandle::Udp(udp)   => udp.as_handle(),
+    };
+
+    fmt
+      .debug_tuple("PendingHandle")
+      .field(&handle.get_type())
+      .field(&handle.as_ptr())
+      .finish()
+  }
 }
 
 impl fmt::Debug for Pipe {
@@ -272,3 +388,13 @@ fn test_layout_pipe() {
     concat!("Alignment of ", stringify!(Pipe))
   );
 }
+
+#[test]
+fn test_accept_pending_loop() {
+  let l = Loop::new().unwrap();
+  let other = Loop::new().unwrap();
+  let mut pipe = OwnedHandle::new(&l, |pipe: &mut Pipe, l| pipe.init(l, true)).unwrap();
+
+  assert_eq!(pipe.get_mut().accept_pending(&other).err(), Some(Error::EINVAL));
+  assert_eq!(pipe.get_mut().accept_pending(&l).err(), Some(Error::EAGAIN));
+}
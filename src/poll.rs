@@ -4,15 +4,18 @@ use bitflags::bitflags;
 
 use crate::Loop;
 use crate::error::Error;
+use crate::context::{self, Slot};
 use crate::handle::{Handle, HandleImpl};
 use crate::bindings::{
-  uv_poll_t, uv_poll_event, uv_poll_cb,
+  uv_poll_t, uv_poll_event, uv_poll_cb, uv_handle_t,
   uv_poll_init, uv_poll_start, uv_poll_stop,
   UV_READABLE, UV_WRITABLE, UV_DISCONNECT, UV_PRIORITIZED
 };
 
 pub type PollCb = uv_poll_cb;
 
+type PollFn = Box<dyn FnMut(&mut Poll, Result<PollEvent, Error>)>;
+
 repr!{
   pub type Poll = uv_poll_t;
 }
@@ -50,10 +53,22 @@ impl From<uv_poll_event> for PollEvent {
   }
 }
 
+unsafe extern "C" fn poll_cb(handle: *mut uv_poll_t, status: i32, events: i32) {
+  let events = if status < 0 {
+    Err(Error::from(status))
+  } else {
+    Ok(PollEvent::from_bits_truncate(events as uv_poll_event))
+  };
+
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut PollFn| {
+    cb(&mut *(handle as *mut Poll), events)
+  });
+}
+
 impl Poll {
-  pub fn init(&mut self, l: &mut Loop, fd: libc::c_int) -> Result<(), Error> {
+  pub fn init(&mut self, l: &Loop, fd: libc::c_int) -> Result<(), Error> {
     let rc = unsafe {
-      uv_poll_init(l.as_mut_ptr(), self as *mut Poll, fd)
+      uv_poll_init(l.as_raw(), self as *mut Poll, fd)
     };
     if rc < 0 {
       return Err( Error::from(rc) );
@@ -71,6 +86,19 @@ impl Poll {
     Ok(())
   }
 
+  /// Same as `start()`, with a closure as callback.
+  ///
+  /// The closure receives the detected events, or the error reported by libuv, in which
+  /// case it is advised to stop the handle.
+  pub fn start_with<F>(&mut self, events: PollEvent, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Poll, Result<PollEvent, Error>) + 'static
+  {
+    self.start(events, Some(poll_cb))?;
+    context::set::<PollFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
   pub fn stop(&mut self) -> Result<(), Error> {
     let rc = unsafe {
       uv_poll_stop(self as *mut Poll)
@@ -1,8 +1,9 @@
+use crate::context::{self, Slot};
 use crate::r#loop::Loop;
 use crate::error::Error;
 use crate::handle::{Handle, HandleImpl};
 use crate::bindings::{
-  uv_prepare_t, uv_prepare_cb, 
+  uv_prepare_t, uv_prepare_cb, uv_handle_t,
   uv_prepare_init, uv_prepare_start, uv_prepare_stop,
 };
 
@@ -12,10 +13,18 @@ repr!{
 
 pub type PrepareCb = uv_prepare_cb;
 
+type PrepareFn = Box<dyn FnMut(&mut Prepare)>;
+
+unsafe extern "C" fn prepare_cb(handle: *mut uv_prepare_t) {
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut PrepareFn| {
+    cb(&mut *(handle as *mut Prepare))
+  });
+}
+
 impl Prepare {
-  pub fn init(&mut self, l: &mut Loop) -> Result<(), Error> {
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
     let rc = unsafe {
-      uv_prepare_init(l.as_mut_ptr(), self.as_mut_ptr())
+      uv_prepare_init(l.as_raw(), self.as_mut_ptr())
     };
 
     if rc < 0 {
@@ -37,6 +46,16 @@ impl Prepare {
     Ok(())
   }
 
+  /// Same as `start()`, with a closure as callback.
+  pub fn start_with<F>(&mut self, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Prepare) + 'static
+  {
+    self.start(Some(prepare_cb))?;
+    context::set::<PrepareFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
   pub fn stop(&mut self) -> Result<(), Error> {
     let rc = unsafe {
       uv_prepare_stop(self.as_mut_ptr())
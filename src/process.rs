@@ -6,12 +6,13 @@ use std::ffi::CStr;
 pub type Char = libc::c_char;
 
 use crate::error::Error;
+use crate::context::{self, Slot};
 use crate::r#loop::Loop;
 use crate::handle::{Handle, HandleImpl};
 use crate::stdio::StdioContainer;
 use crate::bindings::{
   uv_process_kill, uv_spawn,
-  uv_uid_t, uv_gid_t, uv_process_options_t, uv_process_t
+  uv_uid_t, uv_gid_t, uv_process_options_t, uv_process_t, uv_handle_t
 };
 
 repr!{
@@ -27,6 +28,14 @@ repr!{
 pub type ExitCb  = Option<unsafe extern "C" fn(proc: *mut Process, exit_status: i64, term_signal: i32)>;
 pub type CloseCb = Option<unsafe extern "C" fn(handle: *mut Handle)>;
 
+type ExitFn = Box<dyn FnMut(&mut Process, i64, i32)>;
+
+unsafe extern "C" fn exit_cb(handle: *mut uv_process_t, exit_status: i64, term_signal: i32) {
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut ExitFn| {
+    cb(&mut *(handle as *mut Process), exit_status, term_signal)
+  });
+}
+
 impl HandleImpl for Process {
   fn as_handle(&self) -> &Handle {
     unsafe {
@@ -56,9 +65,9 @@ impl Process {
     Ok(())
   }
 
-  pub fn spawn(&mut self, l: &mut Loop, options: ProcessOptions) -> Result<(), Error> {
+  pub fn spawn(&mut self, l: &Loop, options: ProcessOptions) -> Result<(), Error> {
     let rc = unsafe {
-      uv_spawn(l as *mut Loop, self.as_mut_ptr(), &options)
+      uv_spawn(l.as_raw(), self.as_mut_ptr(), &options)
     };
 
     if rc < 0 {
@@ -67,6 +76,18 @@ impl Process {
 
     Ok(())
   }
+
+  /// Same as `spawn()`, with a closure as exit callback, overriding `options.exit_cb`.
+  /// The closure receives the exit status and the signal that terminated the process, if any.
+  pub fn spawn_with<F>(&mut self, l: &Loop, mut options: ProcessOptions, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Process, i64, i32) + 'static
+  {
+    options.exit_cb = Some(exit_cb);
+    self.spawn(l, options)?;
+    context::set::<ExitFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
 }
 
 impl ProcessOptions {
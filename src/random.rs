@@ -0,0 +1,95 @@
+use std::ptr;
+use std::os::raw::{c_int, c_void};
+
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::req::Random;
+use crate::bindings::{size_t, uv_random_t, uv_random};
+
+/// Buffer being filled by an asynchronous `Loop::random()`, stored in the request `data` slot.
+struct RandomData {
+  buf:  Vec<u8>,
+  cb:   Box<dyn FnOnce(Result<Vec<u8>, Error>)>,
+}
+
+/// Fill `buf` with cryptographically strong random bytes, synchronously.
+///
+/// May block indefinitely when not enough entropy is available.
+pub fn fill(buf: &mut [u8]) -> Result<(), Error> {
+  let rc = unsafe {
+    uv_random(ptr::null_mut(), ptr::null_mut(), buf.as_mut_ptr() as *mut c_void, buf.len() as size_t, 0, None)
+  };
+
+  if rc < 0 {
+    return Err(Error::from(rc));
+  }
+
+  Ok(())
+}
+
+unsafe extern "C" fn random_cb(req: *mut uv_random_t, status: c_int, _buf: *mut c_void, _buflen: size_t) {
+  let req = Box::from_raw(req as *mut Random);
+  let data = Box::from_raw(req.data as *mut RandomData);
+  let RandomData { buf, cb } = *data;
+
+  if status < 0 {
+    return cb(Err(Error::from(status)));
+  }
+
+  cb(Ok(buf))
+}
+
+impl Loop {
+  /// Generate `len` cryptographically strong random bytes on the threadpool, passed to `cb`.
+  pub fn random<F>(&self, len: usize, cb: F) -> Result<(), Error>
+  where
+    F: FnOnce(Result<Vec<u8>, Error>) + 'static
+  {
+    let mut data = Box::new(RandomData {
+      buf:  vec![0; len],
+      cb:   Box::new(cb),
+    });
+
+    let buf = data.buf.as_mut_ptr() as *mut c_void;
+    let req = Box::into_raw(Random::boxed());
+
+    unsafe {
+      (*req).data = Box::into_raw(data) as *mut c_void;
+    }
+
+    let rc = unsafe {
+      uv_random(self.as_raw(), req, buf, len as size_t, 0, Some(random_cb))
+    };
+
+    if rc < 0 {
+      unsafe {
+        drop(Box::from_raw((*req).data as *mut RandomData));
+        drop(Box::from_raw(req));
+      }
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+}
+
+#[test]
+fn test_random_fill() {
+  use std::rc::Rc;
+  use std::cell::RefCell;
+  use crate::r#loop::RunMode;
+
+  let mut buf = [0u8; 64];
+  fill(&mut buf).unwrap();
+  assert!(buf.iter().any(|&byte| byte != 0));
+
+  let l = Loop::new().unwrap();
+  let bytes = Rc::new(RefCell::new(None));
+  let result = bytes.clone();
+
+  l.random(32, move |res| *result.borrow_mut() = Some(res)).unwrap();
+  l.run(RunMode::Default).unwrap();
+
+  let bytes = bytes.borrow_mut().take().unwrap().unwrap();
+  assert_eq!(bytes.len(), 32);
+}
@@ -1,8 +1,9 @@
 use std::os::raw::c_void;
 
+use crate::error::Error;
 use crate::bindings::{
   uv_req_t, uv_req_type, 
-  uv_req_get_type, uv_req_get_data, uv_req_set_data,
+  uv_req_get_type, uv_req_get_data, uv_req_set_data, uv_cancel,
   UV_UNKNOWN_REQ, UV_REQ, UV_CONNECT, UV_WRITE, 
   UV_SHUTDOWN, UV_UDP_SEND, UV_FS, UV_WORK,
   UV_GETADDRINFO, UV_GETNAMEINFO, UV_RANDOM, UV_REQ_TYPE_MAX
@@ -107,6 +108,21 @@ pub trait ReqImpl {
       uv_req_set_data(self.as_mut_request().as_mut_ptr(), data)
     }
   }
+
+  /// Cancel a pending request. Fails if the request is executing or has finished executing.
+  /// Only cancellation of `Fs`, `GetAddrInfo`, `GetNameInfo`, `Random` and `Work` requests
+  /// is currently supported. Their callback is then called with `ECANCELED`.
+  fn cancel(&mut self) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_cancel(self.as_mut_request().as_mut_ptr())
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
 }
 
 impl ReqImpl for Req {
@@ -128,6 +144,26 @@ pub use self::connect::{Connect, ConnectCb};
 pub mod shutdown;
 pub use self::shutdown::{Shutdown, ShutdownCb};
 
+pub mod udp_send;
+pub use self::udp_send::{UdpSend, UdpSendCb};
+
+pub mod fs;
+pub use self::fs::{Fs, FsCb};
+
+pub mod work;
+pub use self::work::{Work, WorkCb, AfterWorkCb};
+
+pub mod getaddrinfo;
+pub use self::getaddrinfo::{GetAddrInfo, GetAddrInfoCb};
+
+pub mod getnameinfo;
+pub use self::getnameinfo::{GetNameInfo, GetNameInfoCb};
+
+pub mod random;
+pub use self::random::{Random, RandomCb};
+
+pub(crate) mod owned;
+
 #[test]
 fn test_layout_req_type() {
   use std::mem;
@@ -0,0 +1,97 @@
+use std::os::raw::{c_char, c_void};
+
+use crate::req::{Req, ReqImpl};
+use crate::bindings::{
+  uv_fs_t, uv_stat_t,
+  uv_fs_get_result, uv_fs_get_system_error, uv_fs_get_ptr,
+  uv_fs_get_path, uv_fs_get_statbuf, uv_fs_req_cleanup
+};
+
+pub type FsCb = crate::bindings::uv_fs_cb;
+
+repr!{
+  pub type Fs = uv_fs_t;
+}
+
+impl Fs {
+  /// Result of the request: `< 0` on error, success value otherwise (file descriptor,
+  /// number of bytes, number of entries...). On success with `stat` requests it is `0`.
+  pub fn get_result(&self) -> isize {
+    unsafe {
+      uv_fs_get_result(self.as_ptr()) as isize
+    }
+  }
+
+  /// Platform specific error code - `GetLastError()` on Windows, `-errno` on Unix.
+  pub fn get_system_error(&self) -> i32 {
+    unsafe {
+      uv_fs_get_system_error(self.as_ptr())
+    }
+  }
+
+  /// Operation specific result pointer (`readlink`, `realpath`, `statfs`, `opendir`...).
+  pub fn get_ptr(&self) -> *mut c_void {
+    unsafe {
+      uv_fs_get_ptr(self.as_ptr())
+    }
+  }
+
+  /// Path affecting the request.
+  pub fn get_path(&self) -> *const c_char {
+    unsafe {
+      uv_fs_get_path(self.as_ptr())
+    }
+  }
+
+  /// Result of `stat`, `fstat` and `lstat` requests.
+  pub fn get_statbuf(&mut self) -> &uv_stat_t {
+    unsafe {
+      &*uv_fs_get_statbuf(self.as_mut_ptr())
+    }
+  }
+
+  /// Cleanup request. Must be called after a request is finished to deallocate
+  /// any memory libuv might have allocated.
+  pub fn cleanup(&mut self) {
+    unsafe {
+      uv_fs_req_cleanup(self.as_mut_ptr())
+    }
+  }
+}
+
+impl ReqImpl for Fs {
+  fn as_request(&self) -> &Req {
+    unsafe {
+      let ptr = self.as_ptr() as *const Req;
+      ptr.as_ref().unwrap()
+    }
+  }
+
+  fn as_mut_request(&mut self) -> &mut Req {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Req;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+#[test]
+fn test_layout_fs() {
+  use std::mem;
+  use crate::bindings::uv_req_t;
+  assert_eq!(
+    mem::size_of::<Fs>(),
+    mem::size_of::<uv_fs_t>(),
+    concat!("Size of: ", stringify!(Fs))
+  );
+  assert_eq!(
+    mem::align_of::<Fs>(),
+    mem::align_of::<uv_fs_t>(),
+    concat!("Alignment of ", stringify!(Fs))
+  );
+  assert_eq!(
+    mem::align_of::<Fs>(),
+    mem::align_of::<uv_req_t>(),
+    concat!("Alignment of ", stringify!(Fs))
+  );
+}
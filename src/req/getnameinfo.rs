@@ -0,0 +1,55 @@
+use crate::r#loop::Loop;
+use crate::req::{Req, ReqImpl};
+use crate::bindings::uv_getnameinfo_t;
+
+pub type GetNameInfoCb = crate::bindings::uv_getnameinfo_cb;
+
+repr!{
+  pub type GetNameInfo = uv_getnameinfo_t;
+}
+
+impl GetNameInfo {
+  pub fn get_loop(&self) -> &Loop {
+    unsafe {
+      let l = self.loop_ as *const Loop;
+      l.as_ref().unwrap()
+    }
+  }
+}
+
+impl ReqImpl for GetNameInfo {
+  fn as_request(&self) -> &Req {
+    unsafe {
+      let ptr = self.as_ptr() as *const Req;
+      ptr.as_ref().unwrap()
+    }
+  }
+
+  fn as_mut_request(&mut self) -> &mut Req {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Req;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+#[test]
+fn test_layout_getnameinfo() {
+  use std::mem;
+  use crate::bindings::uv_req_t;
+  assert_eq!(
+    mem::size_of::<GetNameInfo>(),
+    mem::size_of::<uv_getnameinfo_t>(),
+    concat!("Size of: ", stringify!(GetNameInfo))
+  );
+  assert_eq!(
+    mem::align_of::<GetNameInfo>(),
+    mem::align_of::<uv_getnameinfo_t>(),
+    concat!("Alignment of ", stringify!(GetNameInfo))
+  );
+  assert_eq!(
+    mem::align_of::<GetNameInfo>(),
+    mem::align_of::<uv_req_t>(),
+    concat!("Alignment of ", stringify!(GetNameInfo))
+  );
+}
@@ -0,0 +1,128 @@
+//! Requests allocated by the crate and freed by a trampoline once libuv completes them.
+
+use std::any::Any;
+use std::os::raw::c_int;
+
+use crate::error::Error;
+
+/// Completion closure of a write, shutdown, connect or send request.
+pub(crate) type RequestFn = Box<dyn FnOnce(Result<(), Error>)>;
+
+/// Called once libuv completes the request.
+pub(crate) enum Completion<R> {
+  /// Raw libuv callback, called while the request is still allocated.
+  Raw(Option<unsafe extern "C" fn(*mut R, c_int)>),
+  /// Closure receiving the status, called once the request has been freed.
+  Closure(RequestFn),
+}
+
+impl<R> From<RequestFn> for Completion<R> {
+  fn from(cb: RequestFn) -> Self {
+    Completion::Closure(cb)
+  }
+}
+
+/// Request along with its completion and the memory it uses. The request is the first field,
+/// so the pointer handed to libuv is also a pointer to the `OwnedReq`, and the `data` field
+/// of the request is left to the user.
+#[repr(C)]
+pub(crate) struct OwnedReq<R> {
+  req:        R,
+  completion: Completion<R>,
+  /// Kept alive until the request completes, e.g. the data being written.
+  storage:    Option<Box<dyn Any>>,
+}
+
+impl<R: Default> OwnedReq<R> {
+  /// Allocate a request, to be submitted with `OwnedReq::<R>::trampoline` as callback.
+  pub(crate) fn into_raw(completion: Completion<R>, storage: Option<Box<dyn Any>>) -> *mut R {
+    let owned = Box::new(OwnedReq { req: R::default(), completion, storage });
+    Box::into_raw(owned) as *mut R
+  }
+}
+
+impl<R> OwnedReq<R> {
+  /// Free a request that libuv failed to submit, without calling its completion.
+  ///
+  /// # Safety
+  /// `req` must come from `OwnedReq::into_raw()` and must not be used by libuv.
+  pub(crate) unsafe fn abort(req: *mut R) {
+    drop(Box::from_raw(req as *mut OwnedReq<R>))
+  }
+
+  /// Callback passed to libuv: runs the completion and frees the request.
+  pub(crate) unsafe extern "C" fn trampoline(req: *mut R, status: c_int) {
+    let mut owned = Box::from_raw(req as *mut OwnedReq<R>);
+
+    match std::mem::replace(&mut owned.completion, Completion::Raw(None)) {
+      Completion::Raw(cb) => {
+        if let Some(cb) = cb {
+          cb(req, status);
+        }
+        drop(owned);
+      },
+      Completion::Closure(cb) => {
+        drop(owned);
+        cb(if status < 0 { Err(Error::from(status)) } else { Ok(()) })
+      },
+    }
+  }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_trampoline_completions() {
+  use std::rc::Rc;
+  use std::cell::{Cell, RefCell};
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  use crate::buf::Buf;
+  use crate::pipe::Pipe;
+  use crate::handle::OwnedHandle;
+  use crate::r#loop::{Loop, RunMode};
+  use crate::stream::StreamImpl;
+  use crate::bindings::uv_write_t;
+
+  static RAW_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+  unsafe extern "C" fn write_cb(req: *mut uv_write_t, status: c_int) {
+    // The request is still allocated while the raw callback runs.
+    assert!(!(*req).handle.is_null());
+    assert_eq!(status, 0);
+    RAW_CALLS.fetch_add(1, Ordering::SeqCst);
+  }
+
+  let l = Loop::new().unwrap();
+  let mut fds = [0; 2];
+  assert_eq!(unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) }, 0);
+
+  let mut writer = OwnedHandle::new(&l, |pipe: &mut Pipe, l| pipe.init(l, false)).unwrap();
+  writer.get_mut().open(fds[0]).unwrap();
+  let mut reader = OwnedHandle::new(&l, |pipe: &mut Pipe, l| pipe.init(l, false)).unwrap();
+  reader.get_mut().open(fds[1]).unwrap();
+
+  let raw = Buf::from(&b"raw "[..]);
+  writer.get_mut().write(&raw, 1, Some(write_cb)).unwrap();
+
+  let closure = Rc::new(Cell::new(None));
+  let status = closure.clone();
+  // The buffer points to static data.
+  unsafe {
+    writer.get_mut().write_with(&[Buf::from(&b"closure"[..])], move |res| status.set(Some(res))).unwrap();
+  }
+
+  let received = Rc::new(RefCell::new(Vec::new()));
+  let data = received.clone();
+  reader.get_mut().read_start_with(move |stream, res| {
+    data.borrow_mut().extend_from_slice(res.unwrap());
+    if data.borrow().len() == 11 {
+      stream.read_stop().unwrap();
+    }
+  }).unwrap();
+
+  l.run(RunMode::Default).unwrap();
+
+  assert_eq!(RAW_CALLS.load(Ordering::SeqCst), 1);
+  assert_eq!(closure.get(), Some(Ok(())));
+  assert_eq!(&received.borrow()[..], b"raw closure");
+}
@@ -0,0 +1,62 @@
+use crate::udp::Udp;
+use crate::req::{Req, ReqImpl};
+use crate::bindings::uv_udp_send_t;
+
+pub type UdpSendCb = crate::bindings::uv_udp_send_cb;
+
+repr!{
+  pub type UdpSend = uv_udp_send_t;
+}
+
+impl UdpSend {
+  pub fn handle(&self) -> &Udp {
+    unsafe {
+      let handle = self.handle as *const Udp;
+      handle.as_ref().unwrap()
+    }
+  }
+
+  pub fn handle_mut(&mut self) -> &mut Udp {
+    unsafe {
+      let handle = self.handle as *mut Udp;
+      handle.as_mut().unwrap()
+    }
+  }
+}
+
+impl ReqImpl for UdpSend {
+  fn as_request(&self) -> &Req {
+    unsafe {
+      let ptr = self.as_ptr() as *const Req;
+      ptr.as_ref().unwrap()
+    }
+  }
+  
+  fn as_mut_request(&mut self) -> &mut Req {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Req;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+#[test]
+fn test_layout_udp_send() {
+  use std::mem;
+  use crate::bindings::uv_req_t;
+  assert_eq!(
+    mem::size_of::<UdpSend>(),
+    mem::size_of::<uv_udp_send_t>(),
+    concat!("Size of: ", stringify!(UdpSend))
+  );
+  assert_eq!(
+    mem::align_of::<UdpSend>(),
+    mem::align_of::<uv_udp_send_t>(),
+    concat!("Alignment of ", stringify!(UdpSend))
+  );
+  assert_eq!(
+    mem::align_of::<UdpSend>(),
+    mem::align_of::<uv_req_t>(),
+    concat!("Alignment of ", stringify!(UdpSend))
+  );
+}
@@ -0,0 +1,56 @@
+use crate::r#loop::Loop;
+use crate::req::{Req, ReqImpl};
+use crate::bindings::uv_work_t;
+
+pub type WorkCb = crate::bindings::uv_work_cb;
+pub type AfterWorkCb = crate::bindings::uv_after_work_cb;
+
+repr!{
+  pub type Work = uv_work_t;
+}
+
+impl Work {
+  pub fn get_loop(&self) -> &Loop {
+    unsafe {
+      let l = self.loop_ as *const Loop;
+      l.as_ref().unwrap()
+    }
+  }
+}
+
+impl ReqImpl for Work {
+  fn as_request(&self) -> &Req {
+    unsafe {
+      let ptr = self.as_ptr() as *const Req;
+      ptr.as_ref().unwrap()
+    }
+  }
+
+  fn as_mut_request(&mut self) -> &mut Req {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Req;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+#[test]
+fn test_layout_work() {
+  use std::mem;
+  use crate::bindings::uv_req_t;
+  assert_eq!(
+    mem::size_of::<Work>(),
+    mem::size_of::<uv_work_t>(),
+    concat!("Size of: ", stringify!(Work))
+  );
+  assert_eq!(
+    mem::align_of::<Work>(),
+    mem::align_of::<uv_work_t>(),
+    concat!("Alignment of ", stringify!(Work))
+  );
+  assert_eq!(
+    mem::align_of::<Work>(),
+    mem::align_of::<uv_req_t>(),
+    concat!("Alignment of ", stringify!(Work))
+  );
+}
@@ -1,24 +1,33 @@
 use std::fmt;
 
+use crate::context::{self, Slot};
 use crate::r#loop::Loop;
 use crate::error::Error;
 use crate::stream::{Stream, StreamImpl};
 use crate::handle::{Handle, HandleImpl};
 use crate::bindings::{
-  uv_signal_t,
+  uv_signal_t, uv_handle_t,
   uv_signal_init, uv_signal_start, uv_signal_start_oneshot, uv_signal_stop
 };
 
 pub type SignalCb = crate::bindings::uv_signal_cb;
 
+type SignalFn = Box<dyn FnMut(&mut Signal, i32)>;
+
 repr!{
   pub type Signal = uv_signal_t;
 }
 
+unsafe extern "C" fn signal_cb(handle: *mut uv_signal_t, signum: i32) {
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut SignalFn| {
+    cb(&mut *(handle as *mut Signal), signum)
+  });
+}
+
 impl Signal {
-  pub fn init(&mut self, l: &mut Loop) -> Result<(), Error> {
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
     let rc = unsafe {
-      uv_signal_init(l.as_mut_ptr(), self.as_mut_ptr())
+      uv_signal_init(l.as_raw(), self.as_mut_ptr())
     };
     if rc < 0 {
       return Err( Error::from(rc) );
@@ -36,6 +45,16 @@ impl Signal {
     Ok(())
   }
 
+  /// Same as `start()`, with a closure as callback.
+  pub fn start_with<F>(&mut self, signum: i32, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Signal, i32) + 'static
+  {
+    self.start(Some(signal_cb), signum)?;
+    context::set::<SignalFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
   pub fn start_oneshot(&mut self, cb: SignalCb, signum: i32) -> Result<(), Error> {
     let res = unsafe {
       uv_signal_start_oneshot(self.as_mut_ptr(), cb, signum)
@@ -46,6 +65,16 @@ impl Signal {
     Ok(())
   }
 
+  /// Same as `start_oneshot()`, with a closure as callback.
+  pub fn start_oneshot_with<F>(&mut self, signum: i32, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Signal, i32) + 'static
+  {
+    self.start_oneshot(Some(signal_cb), signum)?;
+    context::set::<SignalFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
   pub fn stop(&mut self) -> Result<(), Error> {
     let rc = unsafe {
       uv_signal_stop(self.as_mut_ptr())
@@ -1,24 +1,112 @@
+use std::slice;
+use std::any::Any;
 use std::os::raw::{c_int, c_uint};
 
 use crate::buf::Buf;
 use crate::error::Error;
+use crate::context::{self, Slot};
 use crate::handle::{Handle, HandleImpl};
 use crate::req::{Write, WriteCb, Shutdown, ShutdownCb};
+use crate::req::owned::{OwnedReq, Completion, RequestFn};
 use crate::bindings::{
-  uv_stream_t,
+  uv_stream_t, uv_handle_t, uv_buf_t, size_t, ssize_t,
   uv_is_readable, uv_is_writable, uv_stream_set_blocking,
   uv_listen, uv_accept, uv_read_start, uv_read_stop,
-  uv_write, uv_shutdown
+  uv_write, uv_try_write, uv_try_write2, uv_stream_get_write_queue_size, uv_shutdown
 };
 
 pub type ReadCb = crate::bindings::uv_read_cb;
 pub type AllocCb = crate::bindings::uv_alloc_cb;
 pub type ConnectionCb = crate::bindings::uv_connection_cb;
 
+type ConnectionFn = Box<dyn FnMut(&mut Stream, Result<(), Error>)>;
+/// Read closure, which also gets the allocator the data was read into.
+type ReadFn = Box<dyn FnMut(&mut Stream, Result<&[u8], Error>, &mut dyn ReadAlloc)>;
+
+/// Allocator of the closure-based reads, stored in `Slot::Alloc`.
+pub(crate) type AllocFn = Box<dyn ReadAlloc>;
+
 repr!{
   pub type Stream = uv_stream_t;
 }
 
+fn status(status: c_int) -> Result<(), Error> {
+  if status < 0 {
+    return Err(Error::from(status));
+  }
+  Ok(())
+}
+
+unsafe extern "C" fn connection_cb(server: *mut uv_stream_t, status: c_int) {
+  context::invoke(server as *mut uv_handle_t, Slot::Connection, |cb: &mut ConnectionFn| {
+    cb(&mut *(server as *mut Stream), self::status(status))
+  });
+}
+
+pub(crate) unsafe extern "C" fn alloc_cb(handle: *mut uv_handle_t, suggested_size: size_t, buf: *mut uv_buf_t) {
+  match context::get::<AllocFn>(handle, Slot::Alloc) {
+    Some(alloc) => {
+      let data = alloc.alloc(suggested_size as usize);
+      (*buf).base = data.as_mut_ptr() as *mut _;
+      (*buf).len  = data.len() as size_t;
+    },
+    None => {
+      (*buf).base = std::ptr::null_mut();
+      (*buf).len  = 0;
+    }
+  }
+}
+
+unsafe extern "C" fn read_cb(stream: *mut uv_stream_t, nread: ssize_t, buf: *const uv_buf_t) {
+  let handle = stream as *mut uv_handle_t;
+
+  // The allocator is moved out of its slot while the buffer is in use, so the closure can
+  // replace it (e.g. by restarting the reads) without freeing the data it is reading.
+  context::invoke(handle, Slot::Alloc, |alloc: &mut AllocFn| {
+    if nread != 0 {
+      let data = match nread {
+        n if n < 0 => Err(Error::from(n as c_int)),
+        n => Ok(slice::from_raw_parts((*buf).base as *const u8, n as usize)),
+      };
+
+      context::invoke(handle, Slot::Callback, |cb: &mut ReadFn| {
+        cb(&mut *(stream as *mut Stream), data, &mut **alloc)
+      });
+    }
+
+    // The buffer is handed back even if nothing was read (`EAGAIN`).
+    alloc.release(nread.max(0) as usize);
+  });
+}
+
+fn read_fn<F>(mut cb: F) -> ReadFn
+where
+  F: FnMut(&mut Stream, Result<&[u8], Error>) + 'static
+{
+  Box::new(move |stream, data, _| cb(stream, data))
+}
+
+/// Write `bufs`, pointing into `storage`, which the request keeps alive until it completes.
+fn write_stored(stream: &mut Stream, bufs: &[Buf], storage: Box<dyn Any>, cb: RequestFn) -> Result<(), Error> {
+  // libuv asserts that there is at least one buffer.
+  if bufs.is_empty() {
+    return Err(Error::EINVAL);
+  }
+
+  let req = OwnedReq::into_raw(Completion::from(cb), Some(storage));
+
+  let rc = unsafe {
+    uv_write(req, stream.as_mut_ptr(), bufs.as_ptr(), bufs.len() as c_uint, Some(OwnedReq::<Write>::trampoline))
+  };
+
+  if rc < 0 {
+    unsafe { OwnedReq::abort(req) };
+    return Err(Error::from(rc));
+  }
+
+  Ok(())
+}
+
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub trait StreamImpl: HandleImpl {
   fn as_stream(&self) -> &Stream;
@@ -65,6 +153,17 @@ pub trait StreamImpl: HandleImpl {
     Ok(())
   }
 
+  /// Same as `listen()`, with a closure as connection callback.
+  fn listen_with<F>(&mut self, max_connections: c_int, cb: F) -> Result<(), Error>
+  where
+    Self: Sized,
+    F: FnMut(&mut Stream, Result<(), Error>) + 'static
+  {
+    self.listen(max_connections, Some(connection_cb))?;
+    context::set::<ConnectionFn>(self.as_mut_handle(), Slot::Connection, Box::new(cb));
+    Ok(())
+  }
+
   /// Accept incoming connections from the `ConnectionCb` passed to `listen()`.
   fn accept(&mut self, client: &mut Stream) -> Result<(), Error> {
     let rc = unsafe {
@@ -86,6 +185,61 @@ pub trait StreamImpl: HandleImpl {
     Ok(())
   }
 
+  /// Same as `read_start()`, with a closure as read callback.
+  ///
+  /// Reads go to a buffer owned by the handle, which is reused for every read and released
+  /// when the handle is closed. The closure receives the bytes read, or the error reported by
+  /// libuv (`Error::EOF` once the remote end closed the stream).
+  fn read_start_with<F>(&mut self, cb: F) -> Result<(), Error>
+  where
+    Self: Sized,
+    F: FnMut(&mut Stream, Result<&[u8], Error>) + 'static
+  {
+    let handle = self.as_mut_handle();
+
+    if unsafe { context::get::<AllocFn>(handle.as_mut_ptr(), Slot::Alloc) }.is_none() {
+      context::set::<AllocFn>(handle, Slot::Alloc, Box::<ReadBuffer>::default());
+    }
+
+    self.read_start(Some(alloc_cb), Some(read_cb))?;
+    context::set::<ReadFn>(self.as_mut_handle(), Slot::Callback, read_fn(cb));
+    Ok(())
+  }
+
+  /// Same as `read_start_with()`, reading into the buffers of `alloc` (e.g. a `BufferPool`,
+  /// `SlabBuffer` or `RingBuffer`). The slice passed to the closure is handed back to
+  /// `alloc` once the closure returns.
+  fn read_start_alloc<A, F>(&mut self, alloc: A, cb: F) -> Result<(), Error>
+  where
+    Self: Sized,
+    A: ReadAlloc,
+    F: FnMut(&mut Stream, Result<&[u8], Error>) + 'static
+  {
+    self.read_start(Some(alloc_cb), Some(read_cb))?;
+    context::set::<AllocFn>(self.as_mut_handle(), Slot::Alloc, Box::new(alloc));
+    context::set::<ReadFn>(self.as_mut_handle(), Slot::Callback, read_fn(cb));
+    Ok(())
+  }
+
+  /// Same as `read_start_alloc()`, with the data passed as `Bytes`, which can be kept around.
+  ///
+  /// With a `BytesBuffer`, each read is split off the buffer without copying. Other
+  /// allocators copy the data out of their buffer, which is recycled as usual.
+  #[cfg(feature = "bytes")]
+  fn read_start_bytes<A, F>(&mut self, alloc: A, mut cb: F) -> Result<(), Error>
+  where
+    Self: Sized,
+    A: ReadAlloc,
+    F: FnMut(&mut Stream, Result<bytes::Bytes, Error>) + 'static
+  {
+    let read: ReadFn = Box::new(move |stream, data, alloc| cb(stream, data.map(|d| alloc.freeze(d))));
+
+    self.read_start(Some(alloc_cb), Some(read_cb))?;
+    context::set::<AllocFn>(self.as_mut_handle(), Slot::Alloc, Box::new(alloc));
+    context::set::<ReadFn>(self.as_mut_handle(), Slot::Callback, read);
+    Ok(())
+  }
+
   fn read_stop(&mut self) -> Result<(), Error> {
     let rc = unsafe {
       uv_read_stop(self.as_mut_stream().as_mut_ptr())
@@ -96,27 +250,161 @@ pub trait StreamImpl: HandleImpl {
     Ok(())
   }
 
+  /// Write data to the stream. Buffers are written in order.
+  /// The request is freed once `write_cb` returns.
   fn write(&mut self, bufs: *const Buf, nbufs: c_uint, write_cb: WriteCb) -> Result<(), Error> {
+    let req = OwnedReq::into_raw(Completion::Raw(write_cb), None);
+
     let rc = unsafe {
-      let req = Box::into_raw(Write::boxed()) as *mut Write;
-      uv_write(req, self.as_mut_stream().as_mut_ptr(), bufs, nbufs, write_cb)
+      uv_write(req, self.as_mut_stream().as_mut_ptr(), bufs, nbufs, Some(OwnedReq::<Write>::trampoline))
     };
+
+    if rc < 0 {
+      unsafe { OwnedReq::abort(req) };
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Same as `write()`, with a closure called once the data has been written.
+  /// See `write_owned()` for a safe version.
+  ///
+  /// # Safety
+  /// The memory pointed to by `bufs` must stay valid and unchanged until the closure is called.
+  unsafe fn write_with<F>(&mut self, bufs: &[Buf], cb: F) -> Result<(), Error>
+  where
+    Self: Sized,
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    // libuv asserts that there is at least one buffer.
+    if bufs.is_empty() {
+      return Err(Error::EINVAL);
+    }
+
+    let cb: RequestFn = Box::new(cb);
+    let req = OwnedReq::into_raw(Completion::from(cb), None);
+
+    let rc = uv_write(req, self.as_mut_stream().as_mut_ptr(), bufs.as_ptr(), bufs.len() as c_uint, Some(OwnedReq::<Write>::trampoline));
+
     if rc < 0 {
+      OwnedReq::abort(req);
       return Err(Error::from(rc));
     }
+
     Ok(())
   }
 
+  /// Same as `write_with()`, but takes ownership of the buffers, which are kept alive by the
+  /// request until it completes. All buffers are written with a single vectored write.
+  fn write_owned<B, F>(&mut self, bufs: B, cb: F) -> Result<(), Error>
+  where
+    Self: Sized,
+    B: Into<Vec<Vec<u8>>>,
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    let data: Vec<Vec<u8>> = bufs.into();
+    let bufs: Vec<Buf> = data.iter().map(|buf| Buf::from(&buf[..])).collect();
+    write_stored(self.as_mut_stream(), &bufs, Box::new(data), Box::new(cb))
+  }
+
+  /// Same as `write_owned()`, with `Bytes` buffers.
+  #[cfg(feature = "bytes")]
+  fn write_bytes<B, F>(&mut self, bufs: B, cb: F) -> Result<(), Error>
+  where
+    Self: Sized,
+    B: Into<Vec<bytes::Bytes>>,
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    let data: Vec<bytes::Bytes> = bufs.into();
+    let bufs: Vec<Buf> = data.iter().map(|buf| Buf::from(&buf[..])).collect();
+    write_stored(self.as_mut_stream(), &bufs, Box::new(data), Box::new(cb))
+  }
+
+  /// Same as `write()`, but won't queue a write request if it can't be completed immediately.
+  /// Returns the number of bytes written, which can be less than the supplied buffer size.
+  /// Fails with `EAGAIN` if no data can be sent immediately.
+  fn try_write(&mut self, bufs: &[&[u8]]) -> Result<usize, Error> {
+    let bufs: Vec<Buf> = bufs.iter().map(|buf| Buf::from(*buf)).collect();
+
+    let rc = unsafe {
+      uv_try_write(self.as_mut_stream().as_mut_ptr(), bufs.as_ptr(), bufs.len() as c_uint)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(rc as usize)
+  }
+
+  /// Same as `try_write()`, sending `send_handle` over the stream, which must be an IPC pipe.
+  /// Sending a handle is not supported on Windows, where it fails with `EAGAIN`.
+  fn try_write2<S>(&mut self, bufs: &[&[u8]], send_handle: &mut S) -> Result<usize, Error>
+  where
+    Self: Sized,
+    S: StreamImpl
+  {
+    let bufs: Vec<Buf> = bufs.iter().map(|buf| Buf::from(*buf)).collect();
+
+    let rc = unsafe {
+      uv_try_write2(
+        self.as_mut_stream().as_mut_ptr(), bufs.as_ptr(), bufs.len() as c_uint,
+        send_handle.as_mut_stream().as_mut_ptr()
+      )
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(rc as usize)
+  }
+
+  /// Returns the amount of queued bytes waiting to be sent.
+  fn write_queue_size(&self) -> usize {
+    unsafe {
+      uv_stream_get_write_queue_size(self.as_stream().as_ptr()) as usize
+    }
+  }
+
+  /// Shutdown the outgoing (write) side of a duplex stream. It waits for pending write
+  /// requests to complete. The request is freed once `shutdown_cb` returns.
   fn shutdown(&mut self, shutdown_cb: ShutdownCb) -> Result<(), Error> {
+    let req = OwnedReq::into_raw(Completion::Raw(shutdown_cb), None);
+
     let rc = unsafe {
-      let req = Box::into_raw(Shutdown::boxed()) as *mut Shutdown;
-      uv_shutdown(req, self.as_mut_stream().as_mut_ptr(), shutdown_cb)
+      uv_shutdown(req, self.as_mut_stream().as_mut_ptr(), Some(OwnedReq::<Shutdown>::trampoline))
     };
+
     if rc < 0 {
+      unsafe { OwnedReq::abort(req) };
       return Err(Error::from(rc));
     }
+
     Ok(())
-  }  
+  }
+
+  /// Same as `shutdown()`, with a closure called once pending writes are complete.
+  fn shutdown_with<F>(&mut self, cb: F) -> Result<(), Error>
+  where
+    Self: Sized,
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    let cb: RequestFn = Box::new(cb);
+    let req = OwnedReq::into_raw(Completion::from(cb), None);
+
+    let rc = unsafe {
+      uv_shutdown(req, self.as_mut_stream().as_mut_ptr(), Some(OwnedReq::<Shutdown>::trampoline))
+    };
+
+    if rc < 0 {
+      unsafe { OwnedReq::abort(req) };
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
 }
 
 impl HandleImpl for Stream  {
@@ -145,6 +433,19 @@ impl StreamImpl for Stream  {
   }
 }
 
+pub(crate) mod io;
+
+pub mod alloc;
+pub use self::alloc::{ReadAlloc, BufferPool, SlabBuffer, RingBuffer};
+#[cfg(feature = "bytes")]
+pub use self::alloc::BytesBuffer;
+pub(crate) use self::alloc::ReadBuffer;
+
+#[cfg(feature = "futures-io")]
+pub mod compat;
+#[cfg(feature = "futures-io")]
+pub use self::compat::AsyncStream;
+
 #[test]
 fn test_layout_stream() {
   use std::mem;
@@ -0,0 +1,486 @@
+//! Read buffer allocators for `StreamImpl::read_start_alloc()`.
+
+use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::ops::Range;
+
+/// Provides the buffers libuv reads into.
+///
+/// libuv asks for a buffer right before each read, and the read callback runs right after it,
+/// so a stream has at most one buffer in use at a time: `alloc()` hands it out, `release()`
+/// takes it back once the read callback returned. Returning an empty buffer makes the read
+/// fail with `ENOBUFS`.
+pub trait ReadAlloc: 'static {
+  /// Buffer for the next read. `suggested_size` is the size libuv would like (64KiB).
+  fn alloc(&mut self, suggested_size: usize) -> &mut [u8];
+
+  /// Called once the read callback returned, with the number of bytes read into the buffer
+  /// of the last `alloc()` (`0` if the read failed). The buffer can be reused from then on.
+  fn release(&mut self, nread: usize);
+
+  /// Hand out `data`, just read into the buffer of the last `alloc()`, as `Bytes` for
+  /// `read_start_bytes()`. By default the data is copied and the buffer recycled as usual;
+  /// `BytesBuffer` hands it out without copying.
+  #[cfg(feature = "bytes")]
+  fn freeze(&mut self, data: &[u8]) -> bytes::Bytes {
+    bytes::Bytes::copy_from_slice(data)
+  }
+}
+
+/// Buffer lent to libuv by `alloc_cb` for closure-based reads, reused across reads.
+#[derive(Default)]
+pub(crate) struct ReadBuffer(pub(crate) Vec<u8>);
+
+impl ReadAlloc for ReadBuffer {
+  fn alloc(&mut self, suggested_size: usize) -> &mut [u8] {
+    if self.0.len() < suggested_size {
+      self.0.resize(suggested_size, 0);
+    }
+    &mut self.0
+  }
+
+  fn release(&mut self, _nread: usize) {}
+}
+
+struct Classes {
+  sizes:    Vec<usize>,
+  free:     Vec<Vec<Box<[u8]>>>,
+  max_free: usize,
+}
+
+/// Pool of buffers shared by the streams of a loop, sorted in size classes.
+///
+/// Create one pool per loop and give a clone to each stream. Each stream picks its size class
+/// from its previous read: streams receiving small messages use small buffers, and a stream
+/// moves up one class whenever a read fills its buffer. Buffers go back to the pool once the
+/// read callback returned; each class keeps at most `max_free` of them.
+pub struct BufferPool {
+  classes: Rc<RefCell<Classes>>,
+  /// Class index and buffer of the read in progress.
+  current: Option<(usize, Box<[u8]>)>,
+  class:   usize,
+}
+
+impl BufferPool {
+  /// Pool with 4KiB, 16KiB and 64KiB buffers, keeping up to 64 free buffers per class.
+  pub fn new() -> Self {
+    Self::with_classes(&[4 * 1024, 16 * 1024, 64 * 1024], 64)
+  }
+
+  /// Pool with the given buffer sizes, keeping up to `max_free` free buffers per size.
+  ///
+  /// # Panics
+  /// If `sizes` is empty or contains `0`.
+  pub fn with_classes(sizes: &[usize], max_free: usize) -> Self {
+    assert!(!sizes.is_empty() && !sizes.contains(&0), "invalid buffer pool size classes");
+
+    let mut sizes = sizes.to_vec();
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    let classes = Classes { free: vec![Vec::new(); sizes.len()], sizes, max_free };
+
+    BufferPool { classes: Rc::new(RefCell::new(classes)), current: None, class: 0 }
+  }
+
+  /// Number of free buffers of each size class, smallest first.
+  pub fn free_count(&self) -> Vec<usize> {
+    self.classes.borrow().free.iter().map(Vec::len).collect()
+  }
+}
+
+impl Default for BufferPool {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Clone for BufferPool {
+  /// Handle on the same pool, for another stream.
+  fn clone(&self) -> Self {
+    BufferPool { classes: self.classes.clone(), current: None, class: 0 }
+  }
+}
+
+impl ReadAlloc for BufferPool {
+  fn alloc(&mut self, suggested_size: usize) -> &mut [u8] {
+    let mut classes = self.classes.borrow_mut();
+
+    // Never go above the size libuv asked for, unless even the smallest class is bigger.
+    let fits = classes.sizes.iter().take_while(|&&size| size <= suggested_size).count();
+    let class = self.class.min(fits.saturating_sub(1));
+
+    let size = classes.sizes[class];
+    let buf = classes.free[class].pop().unwrap_or_else(|| vec![0; size].into_boxed_slice());
+    drop(classes);
+
+    &mut self.current.insert((class, buf)).1
+  }
+
+  fn release(&mut self, nread: usize) {
+    if let Some((class, buf)) = self.current.take() {
+      let mut classes = self.classes.borrow_mut();
+
+      // Nothing read (`EAGAIN` or an error) says nothing about the size of the next read.
+      self.class = if nread == 0 {
+        self.class
+      } else if nread == buf.len() {
+        (class + 1).min(classes.sizes.len() - 1)
+      } else {
+        classes.sizes.iter().position(|&size| size >= nread).unwrap_or(class)
+      };
+
+      if classes.free[class].len() < classes.max_free {
+        classes.free[class].push(buf);
+      }
+    }
+  }
+}
+
+impl fmt::Debug for BufferPool {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("BufferPool")
+      .field("sizes", &self.classes.borrow().sizes)
+      .field("free", &self.free_count())
+      .finish()
+  }
+}
+
+/// Fixed buffer owned by a single stream, allocated once and used for every read.
+pub struct SlabBuffer {
+  slab: Box<[u8]>,
+}
+
+impl SlabBuffer {
+  /// Slab of `size` bytes, which bounds the size of each read.
+  pub fn new(size: usize) -> Self {
+    SlabBuffer { slab: vec![0; size].into_boxed_slice() }
+  }
+}
+
+impl ReadAlloc for SlabBuffer {
+  fn alloc(&mut self, _suggested_size: usize) -> &mut [u8] {
+    &mut self.slab
+  }
+
+  fn release(&mut self, _nread: usize) {}
+}
+
+impl fmt::Debug for SlabBuffer {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("SlabBuffer")
+      .field("size", &self.slab.len())
+      .finish()
+  }
+}
+
+struct Ring {
+  buf:  Vec<u8>,
+  /// Offset of the first unread byte.
+  head: usize,
+  /// Number of unread bytes.
+  len:  usize,
+  min:  usize,
+  max:  usize,
+  /// Free space lent to libuv by the last `alloc()`, until `release()`.
+  lent: Option<Range<usize>>,
+}
+
+impl Ring {
+  /// Contiguous free space after the unread data, wrapping around to the start of the buffer.
+  fn free(&self) -> Range<usize> {
+    let end = self.head + self.len;
+
+    if end < self.buf.len() {
+      end..self.buf.len()
+    } else {
+      end - self.buf.len()..self.head
+    }
+  }
+
+  /// Double the buffer, up to `max`, moving the unread data to its start.
+  fn grow(&mut self) {
+    let cap = (self.buf.len() * 2).clamp(self.min, self.max);
+
+    if cap <= self.buf.len() {
+      return;
+    }
+
+    let mut buf = vec![0; cap];
+    let (a, b) = self.slices();
+    buf[..a.len()].copy_from_slice(a);
+    buf[a.len()..a.len() + b.len()].copy_from_slice(b);
+
+    self.buf = buf;
+    self.head = 0;
+  }
+
+  /// Unread data, in order.
+  fn slices(&self) -> (&[u8], &[u8]) {
+    let end = self.head + self.len;
+
+    if end <= self.buf.len() {
+      (&self.buf[self.head..end], &[])
+    } else {
+      (&self.buf[self.head..], &self.buf[..end - self.buf.len()])
+    }
+  }
+}
+
+/// Growable ring buffer accumulating the data read from a stream until it is consumed.
+///
+/// Reads go to the free space after the unread data, wrapping around to the start of the
+/// buffer. When less than `min` contiguous bytes are free, the buffer doubles, up to `max`.
+/// Keep a clone to `read()` or `consume()` the data: what a read callback leaves unread stays
+/// in the buffer, and the bytes of a read are appended once the callback returns.
+///
+/// Once the buffer is full at `max` bytes, the read callback gets `ENOBUFS` instead of data,
+/// and reading resumes once data was consumed (or stops with `read_stop()`).
+#[derive(Clone)]
+pub struct RingBuffer {
+  ring: Rc<RefCell<Ring>>,
+}
+
+impl RingBuffer {
+  /// Ring of `min` bytes, growing up to `max` bytes.
+  pub fn new(min: usize, max: usize) -> Self {
+    let min = min.clamp(1, max.max(1));
+    let ring = Ring { buf: vec![0; min], head: 0, len: 0, min, max: max.max(min), lent: None };
+    RingBuffer { ring: Rc::new(RefCell::new(ring)) }
+  }
+
+  /// Number of unread bytes.
+  pub fn len(&self) -> usize {
+    self.ring.borrow().len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Current size of the buffer.
+  pub fn capacity(&self) -> usize {
+    self.ring.borrow().buf.len()
+  }
+
+  /// Copy unread data into `buf` and consume it. Returns the number of bytes copied.
+  pub fn read(&self, buf: &mut [u8]) -> usize {
+    let len = {
+      let ring = self.ring.borrow();
+      let (a, b) = ring.slices();
+      let n = a.len().min(buf.len());
+      buf[..n].copy_from_slice(&a[..n]);
+      let m = b.len().min(buf.len() - n);
+      buf[n..n + m].copy_from_slice(&b[..m]);
+      n + m
+    };
+
+    self.consume(len);
+    len
+  }
+
+  /// Discard the first `n` unread bytes.
+  pub fn consume(&self, n: usize) {
+    let mut ring = self.ring.borrow_mut();
+    let n = n.min(ring.len);
+    let cap = ring.buf.len();
+
+    ring.head = (ring.head + n) % cap;
+    ring.len -= n;
+
+    // The lent space must stay right after the unread data, so only rewind when nothing is
+    // being read.
+    if ring.len == 0 && ring.lent.is_none() {
+      ring.head = 0;
+    }
+  }
+}
+
+impl ReadAlloc for RingBuffer {
+  fn alloc(&mut self, _suggested_size: usize) -> &mut [u8] {
+    let mut ring = self.ring.borrow_mut();
+
+    if ring.free().len() < ring.min {
+      ring.grow();
+    }
+
+    let free = ring.free();
+    let ptr = ring.buf[free.clone()].as_mut_ptr();
+    ring.lent = Some(free.clone());
+    drop(ring);
+
+    // The free space is not touched by `read()` or `consume()`, and the buffer is only
+    // reallocated by `alloc()`, so it stays valid until the read completes.
+    unsafe { std::slice::from_raw_parts_mut(ptr, free.len()) }
+  }
+
+  fn release(&mut self, nread: usize) {
+    let mut ring = self.ring.borrow_mut();
+
+    if let Some(lent) = ring.lent.take() {
+      // Drained by the read callback: the data read is all that is left.
+      if ring.len == 0 {
+        ring.head = lent.start;
+      }
+      ring.len += nread.min(lent.len());
+    }
+  }
+}
+
+impl fmt::Debug for RingBuffer {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let ring = self.ring.borrow();
+
+    fmt
+      .debug_struct("RingBuffer")
+      .field("len", &ring.len)
+      .field("capacity", &ring.buf.len())
+      .field("max", &ring.max)
+      .finish()
+  }
+}
+
+/// Buffer owned by a single stream, handing out each read as `Bytes` without copying.
+///
+/// Reads go to the spare room of a `BytesMut` of `capacity` bytes, and each read is split off
+/// and frozen. The memory is reused once every `Bytes` split from it was dropped, and a new
+/// buffer is allocated in the meantime.
+#[cfg(feature = "bytes")]
+pub struct BytesBuffer {
+  buf:      bytes::BytesMut,
+  capacity: usize,
+}
+
+#[cfg(feature = "bytes")]
+impl BytesBuffer {
+  pub fn new(capacity: usize) -> Self {
+    BytesBuffer { buf: bytes::BytesMut::new(), capacity: capacity.max(1) }
+  }
+}
+
+#[cfg(feature = "bytes")]
+impl ReadAlloc for BytesBuffer {
+  fn alloc(&mut self, _suggested_size: usize) -> &mut [u8] {
+    // The room left is kept initialized, so it only has to be filled when reclaimed.
+    if self.buf.is_empty() {
+      self.buf.reserve(self.capacity);
+      self.buf.resize(self.buf.capacity(), 0);
+    }
+    &mut self.buf
+  }
+
+  fn release(&mut self, _nread: usize) {}
+
+  fn freeze(&mut self, data: &[u8]) -> bytes::Bytes {
+    self.buf.split_to(data.len()).freeze()
+  }
+}
+
+#[cfg(feature = "bytes")]
+impl fmt::Debug for BytesBuffer {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("BytesBuffer")
+      .field("capacity", &self.capacity)
+      .field("available", &self.buf.len())
+      .finish()
+  }
+}
+
+#[test]
+fn test_buffer_pool_classes() {
+  let mut pool = BufferPool::with_classes(&[16, 4, 64], 1);
+  let mut other = pool.clone();
+
+  assert_eq!(pool.alloc(64 * 1024).len(), 4);
+  pool.release(4);
+  assert_eq!(pool.alloc(64 * 1024).len(), 16);
+  pool.release(10);
+  // A read failing with nothing read keeps the class.
+  assert_eq!(pool.alloc(64 * 1024).len(), 16);
+  pool.release(0);
+  assert_eq!(pool.alloc(64 * 1024).len(), 16);
+  pool.release(10);
+  assert_eq!(pool.alloc(8).len(), 4);
+
+  // The 4 bytes buffer is in use, so the other stream gets a new one.
+  assert_eq!(other.alloc(64 * 1024).len(), 4);
+  other.release(0);
+  pool.release(0);
+  assert_eq!(pool.free_count(), vec![1, 1, 0]);
+}
+
+#[test]
+fn test_ring_buffer_wraps() {
+  let mut ring = RingBuffer::new(4, 8);
+  let reader = ring.clone();
+
+  ring.alloc(64)[..3].copy_from_slice(b"abc");
+  ring.release(3);
+
+  // Less than 4 bytes free after the data, so the ring grows to 8 bytes.
+  let buf = ring.alloc(64);
+  assert_eq!(buf.len(), 5);
+  buf[..5].copy_from_slice(b"defgh");
+  ring.release(5);
+
+  let mut out = [0; 6];
+  assert_eq!(reader.read(&mut out), 6);
+  assert_eq!(&out, b"abcdef");
+
+  // The free space wraps around to the start, in front of the unread "gh".
+  let buf = ring.alloc(64);
+  assert_eq!(buf.len(), 6);
+  buf[..4].copy_from_slice(b"ijkl");
+  ring.release(4);
+
+  assert_eq!((reader.len(), reader.capacity()), (6, 8));
+  assert_eq!(reader.read(&mut out), 6);
+  assert_eq!(&out, b"ghijkl");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ring_buffer_drained_in_read_callback() {
+  use crate::pipe::Pipe;
+  use crate::handle::OwnedHandle;
+  use crate::r#loop::{Loop, RunMode};
+  use crate::stream::StreamImpl;
+
+  let l = Loop::new().unwrap();
+  let mut fds = [0; 2];
+  assert_eq!(unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) }, 0);
+
+  let mut writer = OwnedHandle::new(&l, |pipe: &mut Pipe, l| pipe.init(l, false)).unwrap();
+  writer.get_mut().open(fds[0]).unwrap();
+  let mut reader = OwnedHandle::new(&l, |pipe: &mut Pipe, l| pipe.init(l, false)).unwrap();
+  reader.get_mut().open(fds[1]).unwrap();
+
+  let ring = RingBuffer::new(16, 16);
+  let drain = ring.clone();
+  let received = Rc::new(RefCell::new(Vec::new()));
+  let data = received.clone();
+
+  // The bytes of a read are appended once the closure returns, so it drains the previous ones.
+  reader.get_mut().read_start_alloc(ring.clone(), move |_, res| {
+    res.unwrap();
+    let mut buf = [0; 16];
+    let len = drain.read(&mut buf);
+    data.borrow_mut().extend_from_slice(&buf[..len]);
+  }).unwrap();
+
+  for chunk in [&b"abc"[..], b"def", b"ghi"] {
+    assert_eq!(writer.get_mut().try_write(&[chunk]), Ok(3));
+    l.run(RunMode::Once).unwrap();
+  }
+
+  let mut buf = [0; 16];
+  let len = ring.read(&mut buf);
+  received.borrow_mut().extend_from_slice(&buf[..len]);
+  assert_eq!(&received.borrow()[..], b"abcdefghi");
+
+  reader.get_mut().read_stop().unwrap();
+}
@@ -0,0 +1,169 @@
+//! `futures-io` adapters for libuv streams, enabled by the `futures-io` feature.
+
+use std::io;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::handle::{HandleImpl, OwnedHandle};
+use crate::stream::StreamImpl;
+use crate::stream::io::{Reader, Writer};
+use crate::tcp::{Tcp, TcpStream};
+
+/// Any stream (`Tcp`, `Pipe`, `Tty`, ...) usable as `futures_io::AsyncRead + AsyncWrite`,
+/// driven by the loop of the handle.
+///
+/// Reads return `0` once the remote end closed the stream (`UV_EOF`). Writes go through
+/// `uv_try_write` when the socket accepts data right away, and are queued as write requests
+/// otherwise; `poll_flush` waits for those requests to complete, and `poll_close` shuts down
+/// the write side of the stream.
+pub struct AsyncStream<'l, S: HandleImpl> {
+  handle: OwnedHandle<'l, S>,
+  reader: Reader,
+  writer: Writer,
+}
+
+impl<'l, S: StreamImpl> AsyncStream<'l, S> {
+  /// Wrap an initialized stream handle.
+  pub fn new(handle: OwnedHandle<'l, S>) -> Self {
+    AsyncStream { handle, reader: Reader::default(), writer: Writer::default() }
+  }
+
+  pub fn get_ref(&self) -> &S {
+    &self.handle
+  }
+
+  /// Mutable access to the stream handle.
+  ///
+  /// # Safety
+  /// Same as `OwnedHandle::as_mut()`: the handle must not be moved or overwritten.
+  pub unsafe fn get_mut(&mut self) -> &mut S {
+    self.handle.as_mut()
+  }
+}
+
+impl<'l> From<TcpStream<'l>> for AsyncStream<'l, Tcp> {
+  /// Data already read ahead or queued by the `TcpStream` is kept.
+  fn from(stream: TcpStream<'l>) -> Self {
+    let TcpStream { handle, reader, writer } = stream;
+    AsyncStream { handle, reader, writer }
+  }
+}
+
+impl<S: StreamImpl> AsyncRead for AsyncStream<'_, S> {
+  fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+    let this = self.get_mut();
+    this.reader.poll_read(this.handle.get_mut(), cx, buf).map_err(io::Error::from)
+  }
+}
+
+impl<S: StreamImpl> AsyncWrite for AsyncStream<'_, S> {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    let this = self.get_mut();
+    this.writer.poll_write(this.handle.get_mut(), cx, buf).map_err(io::Error::from)
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    self.writer.poll_flush(cx).map_err(io::Error::from)
+  }
+
+  fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    let this = self.get_mut();
+    this.writer.poll_shutdown(this.handle.get_mut(), cx).map_err(io::Error::from)
+  }
+}
+
+impl<S: HandleImpl> fmt::Debug for AsyncStream<'_, S> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("AsyncStream")
+      .field("type", &self.handle.get_type())
+      .finish()
+  }
+}
+
+#[test]
+fn test_async_stream_loopback() {
+  use std::pin::pin;
+  use std::future::{poll_fn, Future};
+
+  use crate::r#loop::Loop;
+  use crate::tcp::TcpListener;
+  use crate::executor::Oneshot;
+
+  let l = Loop::new().unwrap();
+  let mut listener = TcpListener::bind(&l, "127.0.0.1:0".parse().unwrap()).unwrap();
+  let addr = listener.local_addr().unwrap();
+
+  let blocked = Oneshot::new();
+  let unblock = blocked.clone();
+
+  // Only starts reading once the client is blocked.
+  let server = async {
+    blocked.await;
+    let mut stream = listener.accept().await.unwrap();
+    let mut buf = [0; 16 * 1024];
+    let mut received = 0;
+
+    while let len @ 1.. = stream.read(&mut buf).await.unwrap() {
+      received += len;
+    }
+
+    received
+  };
+
+  let client = async {
+    let mut stream = AsyncStream::from(TcpStream::connect(&l, addr).await.unwrap());
+    let chunk = [7; 16 * 1024];
+    let mut written = 0;
+
+    // Nobody reads yet, so writes are queued until the stream pushes back.
+    poll_fn(|cx| {
+      assert_eq!(Pin::new(&mut stream).poll_write(cx, &[]).map(Result::ok), Poll::Ready(Some(0)));
+
+      loop {
+        match Pin::new(&mut stream).poll_write(cx, &chunk) {
+          Poll::Ready(Ok(len)) => written += len,
+          Poll::Ready(Err(err)) => panic!("{}", err),
+          Poll::Pending => return Poll::Ready(()),
+        }
+      }
+    }).await;
+
+    unblock.set(());
+
+    // The queued writes complete as the server reads, then the write side is shut down.
+    poll_fn(|cx| Pin::new(&mut stream).poll_write(cx, &chunk)).await.map(|len| written += len).unwrap();
+    poll_fn(|cx| Pin::new(&mut stream).poll_close(cx)).await.unwrap();
+
+    written
+  };
+
+  // Both ends borrow the loop, so they are polled together by `block_on`.
+  let (mut server, mut client) = (pin!(server), pin!(client));
+  let (mut received, mut written) = (None, None);
+
+  l.block_on(poll_fn(|cx| {
+    if received.is_none() {
+      received = match server.as_mut().poll(cx) {
+        Poll::Ready(len) => Some(len),
+        Poll::Pending => None,
+      };
+    }
+    if written.is_none() {
+      written = match client.as_mut().poll(cx) {
+        Poll::Ready(len) => Some(len),
+        Poll::Pending => None,
+      };
+    }
+    match received.is_some() && written.is_some() {
+      true => Poll::Ready(()),
+      false => Poll::Pending,
+    }
+  })).unwrap();
+
+  assert!(written.unwrap() > 64 * 1024);
+  assert_eq!(received, written);
+}
@@ -0,0 +1,256 @@
+//! Future-based reads and writes over the closure callbacks of `StreamImpl`.
+
+use std::rc::Rc;
+use std::pin::Pin;
+use std::future::Future;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::task::{Context, Poll, Waker};
+
+use crate::error::Error;
+use crate::executor::Oneshot;
+use crate::stream::{Stream, StreamImpl};
+
+/// Reading is stopped once this many bytes are buffered, until the reader drains them.
+/// Likewise, writing waits once this many bytes are queued in write requests.
+const HIGH_WATER_MARK: usize = 64 * 1024;
+
+#[derive(Default)]
+struct ReadState {
+  buffer:  VecDeque<u8>,
+  eof:     bool,
+  error:   Option<Error>,
+  reading: bool,
+  waker:   Option<Waker>,
+}
+
+impl ReadState {
+  fn is_done(&self) -> bool {
+    self.eof || self.error.is_some()
+  }
+}
+
+/// Buffers the data read from a stream until it is consumed by `poll_read()`.
+#[derive(Default)]
+pub(crate) struct Reader {
+  state: Rc<RefCell<ReadState>>,
+}
+
+impl Reader {
+  /// Copy buffered data into `buf`, starting to read from `stream` if nothing is buffered.
+  /// Resolves to `0` at end of stream.
+  pub(crate) fn poll_read<S>(&self, stream: &mut S, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, Error>>
+  where
+    S: StreamImpl + Sized
+  {
+    let mut state = self.state.borrow_mut();
+
+    if !state.buffer.is_empty() {
+      let len = buf.len().min(state.buffer.len());
+
+      for (dst, src) in buf.iter_mut().zip(state.buffer.drain(..len)) {
+        *dst = src;
+      }
+
+      let resume = !state.reading && !state.is_done() && state.buffer.len() < HIGH_WATER_MARK;
+      drop(state);
+
+      if resume {
+        if let Err(err) = self.start(stream) {
+          return Poll::Ready(Err(err));
+        }
+      }
+
+      return Poll::Ready(Ok(len));
+    }
+
+    if let Some(err) = state.error {
+      return Poll::Ready(Err(err));
+    }
+
+    if state.eof || buf.is_empty() {
+      return Poll::Ready(Ok(0));
+    }
+
+    state.waker = Some(cx.waker().clone());
+    let reading = state.reading;
+    drop(state);
+
+    if !reading {
+      if let Err(err) = self.start(stream) {
+        return Poll::Ready(Err(err));
+      }
+    }
+
+    Poll::Pending
+  }
+
+  fn start<S>(&self, stream: &mut S) -> Result<(), Error>
+  where
+    S: StreamImpl + Sized
+  {
+    let state = self.state.clone();
+
+    stream.read_start_with(move |stream: &mut Stream, data| {
+      let mut state = state.borrow_mut();
+
+      match data {
+        Ok(data) => state.buffer.extend(data),
+        Err(Error::EOF) => state.eof = true,
+        Err(err) => state.error = Some(err),
+      }
+
+      if state.buffer.len() >= HIGH_WATER_MARK || state.is_done() {
+        let _ = stream.read_stop();
+        state.reading = false;
+      }
+
+      let waker = state.waker.take();
+      drop(state);
+
+      if let Some(waker) = waker {
+        waker.wake();
+      }
+    })?;
+
+    self.state.borrow_mut().reading = true;
+    Ok(())
+  }
+}
+
+#[derive(Default)]
+struct WriteState {
+  /// Bytes held by the write requests in flight.
+  queued:   usize,
+  /// Write requests in flight.
+  pending:  usize,
+  error:    Option<Error>,
+  waker:    Option<Waker>,
+}
+
+#[derive(Default)]
+enum ShutdownState {
+  #[default]
+  Open,
+  Pending(Oneshot<Result<(), Error>>),
+  Done,
+}
+
+/// Writes to a stream, immediately when possible and through queued write requests otherwise.
+#[derive(Default)]
+pub(crate) struct Writer {
+  state:    Rc<RefCell<WriteState>>,
+  shutdown: ShutdownState,
+}
+
+impl Writer {
+  /// Write as much of `buf` as the stream accepts without blocking, or queue a copy of it.
+  /// Waits while too much data is already queued.
+  pub(crate) fn poll_write<S>(&self, stream: &mut S, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, Error>>
+  where
+    S: StreamImpl + Sized
+  {
+    // Nothing to write, so a pending error is left for the next write or flush.
+    if buf.is_empty() {
+      return Poll::Ready(Ok(0));
+    }
+
+    let mut state = self.state.borrow_mut();
+
+    if let Some(err) = state.error.take() {
+      return Poll::Ready(Err(err));
+    }
+
+    // Queued data must go out first, so only try to write directly when nothing is pending.
+    if state.pending == 0 {
+      match stream.try_write(&[buf]) {
+        Ok(len) if len > 0 => return Poll::Ready(Ok(len)),
+        Ok(_) | Err(Error::EAGAIN) | Err(Error::ENOSYS) => {},
+        Err(err) => return Poll::Ready(Err(err)),
+      }
+    }
+
+    if state.queued >= HIGH_WATER_MARK {
+      state.waker = Some(cx.waker().clone());
+      return Poll::Pending;
+    }
+
+    let done = self.state.clone();
+    let len = buf.len();
+
+    stream.write_owned(vec![buf.to_vec()], move |status| {
+      let waker = {
+        let mut state = done.borrow_mut();
+        state.pending -= 1;
+        state.queued -= len;
+        if let Err(err) = status {
+          state.error.get_or_insert(err);
+        }
+        state.waker.take()
+      };
+
+      if let Some(waker) = waker {
+        waker.wake();
+      }
+    })?;
+
+    state.pending += 1;
+    state.queued += len;
+
+    Poll::Ready(Ok(buf.len()))
+  }
+
+  /// Wait for the queued write requests to complete.
+  pub(crate) fn poll_flush(&self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+    let mut state = self.state.borrow_mut();
+
+    if let Some(err) = state.error.take() {
+      return Poll::Ready(Err(err));
+    }
+
+    if state.pending > 0 {
+      state.waker = Some(cx.waker().clone());
+      return Poll::Pending;
+    }
+
+    Poll::Ready(Ok(()))
+  }
+
+  /// Flush, then shut down the write side of `stream`.
+  pub(crate) fn poll_shutdown<S>(&mut self, stream: &mut S, cx: &mut Context<'_>) -> Poll<Result<(), Error>>
+  where
+    S: StreamImpl + Sized
+  {
+    loop {
+      match &mut self.shutdown {
+        ShutdownState::Open => {
+          match self.poll_flush(cx) {
+            Poll::Ready(Ok(())) => {},
+            other => return other,
+          }
+
+          self.shutdown = ShutdownState::Pending(shutdown(stream)?);
+        },
+        ShutdownState::Pending(done) => {
+          let status = std::task::ready!(Pin::new(done).poll(cx));
+          self.shutdown = ShutdownState::Done;
+          return Poll::Ready(status);
+        },
+        ShutdownState::Done => return Poll::Ready(Ok(())),
+      }
+    }
+  }
+}
+
+/// Shut down the write side of `stream` once pending writes are complete.
+fn shutdown<S>(stream: &mut S) -> Result<Oneshot<Result<(), Error>>, Error>
+where
+  S: StreamImpl + Sized
+{
+  let done = Oneshot::new();
+  let sender = done.clone();
+
+  stream.shutdown_with(move |status| sender.set(status))?;
+
+  Ok(done)
+}
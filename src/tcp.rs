@@ -1,4 +1,5 @@
 use std::ops::BitOr;
+use std::net::SocketAddr;
 use std::convert::{From, Into};
 
 use crate::bindings::{
@@ -7,11 +8,13 @@ use crate::bindings::{
   uv_connect_cb, uv_stdio_flags,
 };
 
+use crate::net;
 use crate::r#loop::Loop;
 use crate::error::Error;
 use crate::req::Connect;
+use crate::req::owned::{OwnedReq, Completion, RequestFn};
 use crate::stream::{Stream, StreamImpl};
-use crate::handle::{Handle, HandleImpl};
+use crate::handle::{Handle, HandleImpl, SocketImpl};
 
 pub use crate::bindings::{
   UV_READABLE, UV_WRITABLE,
@@ -85,6 +88,8 @@ impl HandleImpl for Tcp {
   }
 }
 
+impl SocketImpl for Tcp {}
+
 impl StreamImpl for Tcp {
   fn as_stream(&self) -> &Stream {
     unsafe {
@@ -103,11 +108,11 @@ impl StreamImpl for Tcp {
 
 impl Tcp {
   /// Initialize a tcp handle. 
-  pub fn init(&mut self, l: &mut Loop) -> Result<(), Error> {
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
     use crate::bindings::*;
 
     let rc = unsafe {
-      uv_tcp_init(l.as_mut_ptr(), self.as_mut_ptr())
+      uv_tcp_init(l.as_raw(), self.as_mut_ptr())
     };
 
     if rc < 0 {
@@ -162,18 +167,44 @@ impl Tcp {
     Ok(())
   }
 
-  /// Connect to the Unix domain socket or the named tcp.
+  /// Establish an IPv4 or IPv6 TCP connection.
+  /// The request is freed once `cb` returns.
   pub fn connect(&mut self, addr: &SockAddr, cb: ConnectFn) -> Result<(), Error> {
     use crate::bindings::*;
 
-    let mut req = Connect::boxed();
-    let req_ptr = req.as_mut_ptr();
-    std::mem::forget(req);
+    let req = OwnedReq::into_raw(Completion::Raw(cb), None);
 
-    unsafe {
-      uv_tcp_connect(req_ptr, self.as_mut_ptr(), addr as *const SockAddr, cb)
+    let rc = unsafe {
+      uv_tcp_connect(req, self.as_mut_ptr(), addr as *const SockAddr, Some(OwnedReq::<Connect>::trampoline))
+    };
+
+    if rc < 0 {
+      unsafe { OwnedReq::abort(req) };
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Same as `connect()`, with a closure called once the connection is established or failed.
+  pub fn connect_with<F>(&mut self, addr: &SockAddr, cb: F) -> Result<(), Error>
+  where
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    use crate::bindings::*;
+
+    let cb: RequestFn = Box::new(cb);
+    let req = OwnedReq::into_raw(Completion::from(cb), None);
+
+    let rc = unsafe {
+      uv_tcp_connect(req, self.as_mut_ptr(), addr as *const SockAddr, Some(OwnedReq::<Connect>::trampoline))
     };
 
+    if rc < 0 {
+      unsafe { OwnedReq::abort(req) };
+      return Err(Error::from(rc));
+    }
+
     Ok(())
   }
 
@@ -247,6 +278,44 @@ impl Tcp {
 
     Ok(size as usize)
   }
+
+  /// Get the local IP and port of the tcp handle.
+  pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+    use crate::bindings::*;
+
+    let mut name = sockaddr_storage::default();
+    let mut size = std::mem::size_of::<sockaddr_storage>() as c_int;
+    let name_ptr = &mut name as *mut sockaddr_storage as *mut SockAddr;
+
+    let rc = unsafe {
+      uv_tcp_getsockname(self.as_ptr(), name_ptr, &mut size)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    unsafe { net::from_sockaddr(name_ptr) }.ok_or(Error::EAFNOSUPPORT)
+  }
+
+  /// Get the remote IP and port of a connected tcp handle.
+  pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
+    use crate::bindings::*;
+
+    let mut name = sockaddr_storage::default();
+    let mut size = std::mem::size_of::<sockaddr_storage>() as c_int;
+    let name_ptr = &mut name as *mut sockaddr_storage as *mut SockAddr;
+
+    let rc = unsafe {
+      uv_tcp_getpeername(self.as_ptr(), name_ptr, &mut size)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    unsafe { net::from_sockaddr(name_ptr) }.ok_or(Error::EAFNOSUPPORT)
+  }
 }
 
 impl From<TcpFlags> for uv_stdio_flags {
@@ -275,6 +344,11 @@ impl BitOr for TcpFlags {
   }
 }
 
+pub mod listener;
+pub use self::listener::TcpListener;
+
+pub mod stream;
+pub use self::stream::TcpStream;
 
 #[test]
 fn test_algnmt_repr() {
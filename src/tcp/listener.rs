@@ -0,0 +1,110 @@
+use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::future::poll_fn;
+use std::task::{Context, Poll, Waker};
+
+use crate::net;
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::handle::OwnedHandle;
+use crate::stream::StreamImpl;
+use crate::bindings::{sockaddr, sockaddr_storage};
+
+use super::{Tcp, TcpStream};
+
+/// Backlog of pending connections passed to `listen()`.
+const BACKLOG: i32 = 128;
+
+#[derive(Default)]
+struct ListenState {
+  /// Connections signaled by libuv and not accepted yet.
+  pending:  usize,
+  error:    Option<Error>,
+  waker:    Option<Waker>,
+}
+
+/// TCP socket listening for connections, accepted as futures on its loop.
+///
+/// libuv stops polling the socket while a connection is pending, so connections are not
+/// accepted faster than `accept()` is called.
+pub struct TcpListener<'l> {
+  l:      &'l Loop,
+  handle: OwnedHandle<'l, Tcp>,
+  state:  Rc<RefCell<ListenState>>,
+}
+
+impl<'l> TcpListener<'l> {
+  /// Bind a socket to `addr` and start listening for connections.
+  pub fn bind(l: &'l Loop, addr: SocketAddr) -> Result<Self, Error> {
+    let mut handle = OwnedHandle::new(l, Tcp::init)?;
+
+    let (storage, _) = net::to_sockaddr(&addr);
+    handle.get_mut().bind(unsafe { &*(&storage as *const sockaddr_storage as *const sockaddr) })?;
+
+    let state = Rc::new(RefCell::new(ListenState::default()));
+    let listen = state.clone();
+
+    handle.get_mut().listen_with(BACKLOG, move |_, status| {
+      let waker = {
+        let mut state = listen.borrow_mut();
+        match status {
+          Ok(()) => state.pending += 1,
+          Err(err) => state.error = Some(err),
+        }
+        state.waker.take()
+      };
+
+      if let Some(waker) = waker {
+        waker.wake();
+      }
+    })?;
+
+    Ok(TcpListener { l, handle, state })
+  }
+
+  /// Wait for the next connection and accept it.
+  pub async fn accept(&mut self) -> Result<TcpStream<'l>, Error> {
+    poll_fn(|cx| self.poll_accept(cx)).await
+  }
+
+  /// Poll for the next connection, accepting it once libuv has signaled one.
+  pub fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<Result<TcpStream<'l>, Error>> {
+    let mut state = self.state.borrow_mut();
+
+    if let Some(err) = state.error.take() {
+      return Poll::Ready(Err(err));
+    }
+
+    if state.pending == 0 {
+      state.waker = Some(cx.waker().clone());
+      return Poll::Pending;
+    }
+
+    state.pending -= 1;
+    drop(state);
+
+    Poll::Ready(self.accept_pending())
+  }
+
+  fn accept_pending(&mut self) -> Result<TcpStream<'l>, Error> {
+    let mut client = OwnedHandle::new(self.l, Tcp::init)?;
+    self.handle.get_mut().accept(client.get_mut().as_mut_stream())?;
+    Ok(TcpStream::from(client))
+  }
+
+  /// Get the local IP and port the listener is bound to.
+  pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+    self.handle.local_addr()
+  }
+}
+
+impl fmt::Debug for TcpListener<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("TcpListener")
+      .field("local_addr", &self.local_addr())
+      .finish()
+  }
+}
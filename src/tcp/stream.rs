@@ -0,0 +1,175 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::future::poll_fn;
+use std::task::{Context, Poll};
+
+use crate::net;
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::executor::Oneshot;
+use crate::handle::OwnedHandle;
+use crate::stream::io::{Reader, Writer};
+use crate::bindings::{sockaddr, sockaddr_storage};
+
+use super::Tcp;
+
+/// Connected TCP socket with future-based reads and writes, driven by its loop.
+///
+/// Data is read ahead into an internal buffer. Reading is paused while that buffer is full,
+/// so a peer cannot send faster than the data is consumed.
+pub struct TcpStream<'l> {
+  pub(crate) handle: OwnedHandle<'l, Tcp>,
+  pub(crate) reader: Reader,
+  pub(crate) writer: Writer,
+}
+
+impl<'l> TcpStream<'l> {
+  /// Open a connection to `addr`.
+  pub async fn connect(l: &'l Loop, addr: SocketAddr) -> Result<TcpStream<'l>, Error> {
+    let mut handle = OwnedHandle::new(l, Tcp::init)?;
+
+    let done = Oneshot::new();
+    let sender = done.clone();
+
+    let (storage, _) = net::to_sockaddr(&addr);
+    handle.get_mut().connect_with(
+      unsafe { &*(&storage as *const sockaddr_storage as *const sockaddr) },
+      move |status| sender.set(status)
+    )?;
+
+    done.await?;
+    Ok(TcpStream::from(handle))
+  }
+
+  /// Read some bytes into `buf`, returning how many were read, or `0` at end of stream.
+  pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+    poll_fn(|cx| self.poll_read(cx, buf)).await
+  }
+
+  /// Poll for some bytes to read into `buf`.
+  pub fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+    self.reader.poll_read(self.handle.get_mut(), cx, buf)
+  }
+
+  /// Write all of `buf`, resolving once it has been handed to the socket.
+  pub async fn write_all(&mut self, mut buf: &[u8]) -> Result<(), Error> {
+    while !buf.is_empty() {
+      let len = poll_fn(|cx| self.writer.poll_write(self.handle.get_mut(), cx, buf)).await?;
+      buf = &buf[len..];
+    }
+
+    poll_fn(|cx| self.writer.poll_flush(cx)).await
+  }
+
+  /// Shut down the write side of the connection, after pending writes are complete.
+  pub async fn shutdown(&mut self) -> Result<(), Error> {
+    poll_fn(|cx| self.writer.poll_shutdown(self.handle.get_mut(), cx)).await
+  }
+
+  /// Enable TCP_NODELAY, which disables Nagle’s algorithm.
+  pub fn set_nodelay(&mut self, enable: bool) -> Result<(), Error> {
+    self.handle.get_mut().nodelay(enable)
+  }
+
+  /// Get the local IP and port of the connection.
+  pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+    self.handle.local_addr()
+  }
+
+  /// Get the remote IP and port of the connection.
+  pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
+    self.handle.peer_addr()
+  }
+}
+
+impl<'l> From<OwnedHandle<'l, Tcp>> for TcpStream<'l> {
+  fn from(handle: OwnedHandle<'l, Tcp>) -> Self {
+    TcpStream { handle, reader: Reader::default(), writer: Writer::default() }
+  }
+}
+
+impl fmt::Debug for TcpStream<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("TcpStream")
+      .field("local_addr", &self.local_addr())
+      .field("peer_addr", &self.peer_addr())
+      .finish()
+  }
+}
+
+#[test]
+fn test_tcp_loopback() {
+  use std::pin::pin;
+  use std::future::Future;
+
+  use super::TcpListener;
+
+  let l = Loop::new().unwrap();
+  let mut listener = TcpListener::bind(&l, "127.0.0.1:0".parse().unwrap()).unwrap();
+  let addr = listener.local_addr().unwrap();
+  let data: Vec<u8> = (0..256 * 1024).map(|i| i as u8).collect();
+
+  // Send everything back once the client shut down its side.
+  let server = async {
+    let mut stream = listener.accept().await?;
+    let mut received = Vec::new();
+    let mut buf = [0; 4096];
+
+    loop {
+      match stream.read(&mut buf).await? {
+        0 => break,
+        len => received.extend_from_slice(&buf[..len]),
+      }
+    }
+
+    stream.write_all(&received).await?;
+    stream.shutdown().await
+  };
+
+  let client = async {
+    let mut stream = TcpStream::connect(&l, addr).await?;
+    assert_eq!(stream.peer_addr(), Ok(addr));
+
+    stream.write_all(&data).await?;
+    stream.shutdown().await?;
+
+    let mut echoed = Vec::new();
+    let mut buf = [0; 4096];
+
+    loop {
+      match stream.read(&mut buf).await? {
+        0 => break,
+        len => echoed.extend_from_slice(&buf[..len]),
+      }
+    }
+
+    Ok::<_, Error>(echoed)
+  };
+
+  // Both ends borrow the loop, so they are polled together by `block_on`.
+  let (mut server, mut client) = (pin!(server), pin!(client));
+  let (mut served, mut echoed) = (None, None);
+
+  l.block_on(poll_fn(|cx| {
+    if served.is_none() {
+      served = match server.as_mut().poll(cx) {
+        Poll::Ready(res) => Some(res),
+        Poll::Pending => None,
+      };
+    }
+    if echoed.is_none() {
+      echoed = match client.as_mut().poll(cx) {
+        Poll::Ready(res) => Some(res),
+        Poll::Pending => None,
+      };
+    }
+    match served.is_some() && echoed.is_some() {
+      true => Poll::Ready(()),
+      false => Poll::Pending,
+    }
+  })).unwrap();
+
+  assert_eq!(served, Some(Ok(())));
+  assert_eq!(echoed.unwrap().as_deref(), Ok(&data[..]));
+}
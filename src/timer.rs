@@ -1,22 +1,31 @@
 use std::fmt;
 
 use crate::bindings::{
-  uv_timer_cb, uv_timer_t,
+  uv_timer_cb, uv_timer_t, uv_handle_t,
   uv_timer_init, uv_timer_start, uv_timer_stop
 };
 
+use crate::context::{self, Slot};
 use crate::r#loop::Loop;
 use crate::error::Error;
 use crate::handle::{Handle, HandleImpl};
 
+type TimerFn = Box<dyn FnMut(&mut Timer)>;
+
 repr!{
   pub type Timer = uv_timer_t;
 }
 
+unsafe extern "C" fn timer_cb(handle: *mut uv_timer_t) {
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut TimerFn| {
+    cb(&mut *(handle as *mut Timer))
+  });
+}
+
 impl Timer {
-  pub fn init(&mut self, l: &mut Loop) -> Result<(), Error> {
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
     let rc = unsafe {
-      uv_timer_init(l.as_mut_ptr(), self.as_mut_ptr())
+      uv_timer_init(l.as_raw(), self.as_mut_ptr())
     };
     if rc < 0 {
       return Err(Error::from(rc));
@@ -34,6 +43,16 @@ impl Timer {
     Ok(())
   }
 
+  /// Same as `start()`, with a closure as callback.
+  pub fn start_with<F>(&mut self, timeout: u64, repeat: u64, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Timer) + 'static
+  {
+    self.start(Some(timer_cb), timeout, repeat)?;
+    context::set::<TimerFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
   pub fn stop(&mut self) -> Result<(), Error> {
     let rc = unsafe {
       uv_timer_stop(self.as_mut_ptr())
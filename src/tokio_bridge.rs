@@ -0,0 +1,104 @@
+//! Run a `Loop` inside a tokio runtime, enabled by the `tokio` feature.
+//!
+//! The backend file descriptor of the loop (its epoll / kqueue instance) is registered with
+//! the tokio reactor. The loop is run without blocking for as long as it stays readable, and
+//! a tokio timer is armed from the loop's backend timeout so libuv timers fire on time.
+
+use std::io;
+use std::pin::Pin;
+use std::future::{poll_fn, Future};
+use std::time::Duration;
+use std::task::Poll;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use ::tokio::io::Interest;
+use ::tokio::io::unix::AsyncFd;
+use ::tokio::time::{sleep, Sleep};
+
+use crate::r#loop::{Loop, RunMode};
+
+/// Backend file descriptor of a loop, owned by the loop itself.
+struct BackendFd(RawFd);
+
+impl AsRawFd for BackendFd {
+  fn as_raw_fd(&self) -> RawFd {
+    self.0
+  }
+}
+
+/// Whether `fd` has events to report right now.
+fn is_readable(fd: RawFd) -> bool {
+  let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+  unsafe { libc::poll(&mut pollfd, 1, 0) > 0 && pollfd.revents & libc::POLLIN != 0 }
+}
+
+/// Drive `l` from the current tokio runtime, until it has no more active handles or requests.
+///
+/// Must be awaited on the thread owning the loop, e.g. from `Runtime::block_on` or a
+/// `LocalSet`. Callbacks of the loop run on that thread, from within this future.
+pub async fn drive(l: &Loop) -> io::Result<()> {
+  let fd = l.backend_fd().ok_or(crate::Error::ENOSYS)?;
+  let fd = AsyncFd::with_interest(BackendFd(fd), Interest::READABLE)?;
+
+  loop {
+    l.run(RunMode::NoWait)?;
+
+    if !l.alive() {
+      return Ok(());
+    }
+
+    let timeout = l.backend_timeout();
+
+    // Callbacks are pending already.
+    if timeout == Some(0) {
+      ::tokio::task::yield_now().await;
+      continue;
+    }
+
+    let mut timer: Option<Pin<Box<Sleep>>> = timeout.map(|ms| Box::pin(sleep(Duration::from_millis(ms))));
+
+    poll_fn(|cx| {
+      while let Poll::Ready(guard) = fd.poll_read_ready(cx) {
+        // tokio only signals new events, so the readiness is kept while the loop has I/O
+        // left to process, e.g. when a run did not handle all of it.
+        if is_readable(fd.get_ref().0) {
+          return Poll::Ready(Ok::<(), io::Error>(()));
+        }
+        guard?.clear_ready();
+      }
+
+      match timer.as_mut() {
+        Some(timer) => timer.as_mut().poll(cx).map(Ok),
+        None => Poll::Pending,
+      }
+    }).await?;
+  }
+}
+
+#[tokio::test]
+async fn test_drive_timer() {
+  use std::rc::Rc;
+  use std::cell::Cell;
+  use std::time::Instant;
+
+  use crate::timer::Timer;
+  use crate::handle::OwnedHandle;
+
+  let l = Loop::new().unwrap();
+  let mut timer = OwnedHandle::new(&l, Timer::init).unwrap();
+  let ticks = Rc::new(Cell::new(0));
+  let count = ticks.clone();
+
+  timer.get_mut().start_with(20, 10, move |timer| {
+    count.set(count.get() + 1);
+    if count.get() == 3 {
+      timer.stop().unwrap();
+    }
+  }).unwrap();
+
+  let start = Instant::now();
+  drive(&l).await.unwrap();
+
+  assert_eq!(ticks.get(), 3);
+  assert!(start.elapsed() >= Duration::from_millis(40));
+}
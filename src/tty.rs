@@ -0,0 +1,231 @@
+use std::fmt;
+use std::convert::From;
+use std::os::raw::c_int;
+
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::stream::{Stream, StreamImpl};
+use crate::handle::{Handle, HandleImpl};
+use crate::bindings::{
+  uv_tty_t, uv_file, uv_tty_mode_t, uv_tty_vtermstate_t,
+  uv_tty_init, uv_tty_set_mode, uv_tty_reset_mode, uv_tty_get_winsize,
+  uv_tty_set_vterm_state, uv_tty_get_vterm_state,
+  UV_TTY_MODE_NORMAL, UV_TTY_MODE_RAW, UV_TTY_MODE_IO,
+  UV_TTY_SUPPORTED, UV_TTY_UNSUPPORTED,
+};
+
+pub type Fd = uv_file;
+
+repr!{
+  pub type Tty = uv_tty_t;
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TtyMode {
+  /// Initial/normal terminal mode.
+  Normal  = UV_TTY_MODE_NORMAL,
+  /// Raw input mode (On Windows, ENABLE_WINDOW_INPUT is also enabled).
+  Raw     = UV_TTY_MODE_RAW,
+  /// Binary-safe I/O mode for IPC (Unix-only).
+  Io      = UV_TTY_MODE_IO,
+}
+
+impl From<TtyMode> for uv_tty_mode_t {
+  fn from(mode: TtyMode) -> uv_tty_mode_t {
+    match mode {
+      TtyMode::Normal => UV_TTY_MODE_NORMAL,
+      TtyMode::Raw    => UV_TTY_MODE_RAW,
+      TtyMode::Io     => UV_TTY_MODE_IO,
+    }
+  }
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VtermState {
+  /// The console supports ANSI / virtual terminal sequences.
+  Supported   = UV_TTY_SUPPORTED,
+  /// The console does not support virtual terminal sequences.
+  Unsupported = UV_TTY_UNSUPPORTED,
+}
+
+impl From<VtermState> for uv_tty_vtermstate_t {
+  fn from(state: VtermState) -> uv_tty_vtermstate_t {
+    match state {
+      VtermState::Supported   => UV_TTY_SUPPORTED,
+      VtermState::Unsupported => UV_TTY_UNSUPPORTED,
+    }
+  }
+}
+
+impl From<uv_tty_vtermstate_t> for VtermState {
+  fn from(i: uv_tty_vtermstate_t) -> Self {
+    match i {
+      UV_TTY_SUPPORTED    => VtermState::Supported,
+      UV_TTY_UNSUPPORTED  => VtermState::Unsupported,
+      _                   => unreachable!()
+    }
+  }
+}
+
+impl HandleImpl for Tty {
+  fn as_handle(&self) -> &Handle {
+    unsafe {
+      let ptr = self.as_ptr() as *const Handle;
+      ptr.as_ref().unwrap()
+    }
+  }
+
+  fn as_mut_handle(&mut self) -> &mut Handle {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Handle;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+impl StreamImpl for Tty {
+  fn as_stream(&self) -> &Stream {
+    unsafe {
+      let ptr = self.as_ptr() as *const Stream;
+      ptr.as_ref().unwrap()
+    }
+  }
+
+  fn as_mut_stream(&mut self) -> &mut Stream {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Stream;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+impl Tty {
+  /// Initialize a new TTY stream with the given file descriptor.
+  /// Usually the file descriptor will be 0 = stdin, 1 = stdout or 2 = stderr.
+  /// On Unix the handle will reopen the tty (`/dev/tty`) when possible, so that
+  /// the file descriptor is not shared with other processes.
+  pub fn init(&mut self, l: &Loop, fd: Fd) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_tty_init(l.as_raw(), self.as_mut_ptr(), fd, 0)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Set the TTY using the specified terminal mode.
+  pub fn set_mode(&mut self, mode: TtyMode) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_tty_set_mode(self.as_mut_ptr(), mode.into())
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// To be called when the program exits. Resets TTY settings to default values for the next process to take over.
+  /// This function is async signal-safe on Unix platforms but can fail with `EBUSY`
+  /// if you call it when execution is inside `set_mode()`.
+  pub fn reset_mode() -> Result<(), Error> {
+    let rc = unsafe {
+      uv_tty_reset_mode()
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Gets the current window size as `(width, height)`.
+  pub fn get_winsize(&mut self) -> Result<(i32, i32), Error> {
+    let mut width: c_int = 0;
+    let mut height: c_int = 0;
+
+    let rc = unsafe {
+      uv_tty_get_winsize(self.as_mut_ptr(), &mut width, &mut height)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok((width, height))
+  }
+
+  /// Controls whether console virtual terminal sequences are processed by libuv or the console.
+  /// Useful in particular for enabling ConEmu support of ANSI X3.64 and Xterm 256 colors.
+  /// This function is only meaningful on Windows systems. On Unix it is silently ignored.
+  pub fn set_vterm_state(state: VtermState) {
+    unsafe {
+      uv_tty_set_vterm_state(state.into())
+    }
+  }
+
+  /// Get the current state of whether console virtual terminal sequences are handled by libuv or the console.
+  /// This function is not implemented on Unix, where it returns `ENOTSUP`.
+  pub fn get_vterm_state() -> Result<VtermState, Error> {
+    let mut state: uv_tty_vtermstate_t = UV_TTY_SUPPORTED;
+
+    let rc = unsafe {
+      uv_tty_get_vterm_state(&mut state)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(VtermState::from(state))
+  }
+}
+
+impl fmt::Debug for Tty {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("Tty")
+      // UV_HANDLE_FIELDS
+      .field("data",  &self.data)
+      .field("loop",  &self.loop_)
+      .field("type",  &self.type_)
+      .field("close_cb",  &self.close_cb)
+      .field("handle_queue",  &self.handle_queue)
+      .field("next_closing",  &self.next_closing)
+      .field("flags",  &self.flags)
+      // UV_STREAM_FIELDS
+      .field("write_queue_size",  &self.write_queue_size)
+      .field("alloc_cb",  &self.alloc_cb)
+      .field("read_cb",  &self.read_cb)
+      // UV_TTY_PRIVATE_FIELDS
+      .field("mode",  &self.mode)
+      .finish()
+  }
+}
+
+#[test]
+fn test_layout_tty() {
+  use std::mem;
+  assert_eq!(
+    mem::size_of::<Tty>(),
+    mem::size_of::<uv_tty_t>(),
+    concat!("Size of: ", stringify!(Tty))
+  );
+  assert_eq!(
+    mem::align_of::<Tty>(),
+    mem::align_of::<Stream>(),
+    concat!("Alignment of ", stringify!(Tty))
+  );
+  assert_eq!(
+    mem::align_of::<Tty>(),
+    mem::align_of::<Handle>(),
+    concat!("Alignment of ", stringify!(Tty))
+  );
+}
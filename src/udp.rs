@@ -0,0 +1,636 @@
+use std::fmt;
+use std::slice;
+use std::any::Any;
+use std::ffi::CString;
+use std::convert::From;
+use std::net::{IpAddr, SocketAddr};
+use std::os::raw::{c_int, c_uint, c_char};
+
+use bitflags::bitflags;
+
+use crate::buf::Buf;
+use crate::net;
+use crate::r#loop::Loop;
+use crate::error::Error;
+use crate::context::{self, Slot};
+use crate::stream::{self, AllocCb, AllocFn, ReadBuffer};
+use crate::req::owned::{OwnedReq, Completion, RequestFn};
+use crate::req::{UdpSend, UdpSendCb};
+use crate::handle::{Handle, HandleImpl, SocketImpl};
+use crate::bindings::{
+  sockaddr, sockaddr_storage, uv_os_sock_t, uv_buf_t, uv_handle_t, ssize_t,
+  uv_udp_t, uv_udp_flags, uv_membership,
+  uv_udp_init, uv_udp_init_ex, uv_udp_open, uv_udp_bind,
+  uv_udp_connect, uv_udp_getpeername, uv_udp_getsockname,
+  uv_udp_set_membership, uv_udp_set_source_membership,
+  uv_udp_set_multicast_loop, uv_udp_set_multicast_ttl, uv_udp_set_multicast_interface,
+  uv_udp_set_broadcast, uv_udp_set_ttl,
+  uv_udp_send, uv_udp_try_send, uv_udp_recv_start, uv_udp_recv_stop,
+  uv_udp_using_recvmmsg, uv_udp_get_send_queue_size, uv_udp_get_send_queue_count,
+  UV_UDP_IPV6ONLY, UV_UDP_PARTIAL, UV_UDP_REUSEADDR, UV_UDP_MMSG_CHUNK,
+  UV_UDP_MMSG_FREE, UV_UDP_LINUX_RECVERR, UV_UDP_RECVMMSG,
+  UV_LEAVE_GROUP, UV_JOIN_GROUP,
+};
+
+pub type SockFd     = uv_os_sock_t;
+pub type UdpRecvCb  = crate::bindings::uv_udp_recv_cb;
+
+type RecvFn = Box<dyn FnMut(&mut Udp, Result<&[u8], Error>, Option<SocketAddr>, UdpFlags)>;
+
+repr!{
+  pub type Udp = uv_udp_t;
+}
+
+bitflags! {
+  pub struct UdpFlags: uv_udp_flags {
+    /// Disables dual stack mode.
+    const IPV6ONLY      = UV_UDP_IPV6ONLY;
+    /// Indicates message was truncated because read buffer was too small.
+    const PARTIAL       = UV_UDP_PARTIAL;
+    /// Indicates if SO_REUSEADDR will be set when binding the handle.
+    const REUSEADDR     = UV_UDP_REUSEADDR;
+    /// Indicates that the message was received by recvmmsg.
+    const MMSG_CHUNK    = UV_UDP_MMSG_CHUNK;
+    /// Indicates that the buffer provided has been fully utilized by recvmmsg.
+    const MMSG_FREE     = UV_UDP_MMSG_FREE;
+    /// Indicates if IP_RECVERR/IPV6_RECVERR will be set when binding the handle.
+    const LINUX_RECVERR = UV_UDP_LINUX_RECVERR;
+    /// Indicates that recvmmsg should be used, if available.
+    const RECVMMSG      = UV_UDP_RECVMMSG;
+  }
+}
+
+impl From<UdpFlags> for c_uint {
+  fn from(flags: UdpFlags) -> c_uint {
+    flags.bits as c_uint
+  }
+}
+
+impl From<c_uint> for UdpFlags {
+  fn from(i: c_uint) -> Self {
+    UdpFlags::from_bits_truncate(i)
+  }
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Membership {
+  LeaveGroup  = UV_LEAVE_GROUP,
+  JoinGroup   = UV_JOIN_GROUP,
+}
+
+impl From<Membership> for uv_membership {
+  fn from(membership: Membership) -> uv_membership {
+    match membership {
+      Membership::LeaveGroup  => UV_LEAVE_GROUP,
+      Membership::JoinGroup   => UV_JOIN_GROUP,
+    }
+  }
+}
+
+unsafe extern "C" fn recv_cb(handle: *mut uv_udp_t, nread: ssize_t, buf: *const uv_buf_t, addr: *const sockaddr, flags: c_uint) {
+  // Nothing left to read.
+  if nread == 0 && addr.is_null() {
+    return;
+  }
+
+  let data = if nread < 0 {
+    Err(Error::from(nread as c_int))
+  } else {
+    Ok(slice::from_raw_parts((*buf).base as *const u8, nread as usize))
+  };
+
+  context::invoke(handle as *mut uv_handle_t, Slot::Callback, |cb: &mut RecvFn| {
+    cb(&mut *(handle as *mut Udp), data, net::from_sockaddr(addr), UdpFlags::from(flags))
+  });
+}
+
+impl HandleImpl for Udp {
+  fn as_handle(&self) -> &Handle {
+    unsafe {
+      let ptr = self.as_ptr() as *const Handle;
+      ptr.as_ref().unwrap()
+    }
+  }
+
+  fn as_mut_handle(&mut self) -> &mut Handle {
+    unsafe {
+      let ptr = self.as_mut_ptr() as *mut Handle;
+      ptr.as_mut().unwrap()
+    }
+  }
+}
+
+impl SocketImpl for Udp {}
+
+fn ip_to_cstring(ip: &IpAddr) -> CString {
+  CString::new(ip.to_string()).unwrap()
+}
+
+fn opt_ip_to_cstring(ip: Option<&IpAddr>) -> Option<CString> {
+  ip.map(ip_to_cstring)
+}
+
+fn opt_cstr_ptr(s: &Option<CString>) -> *const c_char {
+  s.as_ref().map_or(std::ptr::null(), |s| s.as_ptr())
+}
+
+impl Udp {
+  /// Initialize a new UDP handle. The actual socket is created lazily.
+  pub fn init(&mut self, l: &Loop) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_init(l.as_raw(), self.as_mut_ptr())
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Initialize the handle with the specified flags (only `UdpFlags::RECVMMSG` is supported).
+  /// A socket is created for `domain` (`AF_INET` or `AF_INET6`) unless `AF_UNSPEC` is passed.
+  pub fn init_ex(&mut self, l: &Loop, domain: c_int, flags: UdpFlags) -> Result<(), Error> {
+    let flags = c_uint::from(flags);
+
+    // libuv takes the domain in the lower 8 bits of the flags.
+    if domain as c_uint & !0xff != 0 || flags & 0xff != 0 {
+      return Err(Error::EINVAL);
+    }
+
+    let rc = unsafe {
+      uv_udp_init_ex(l.as_raw(), self.as_mut_ptr(), domain as c_uint | flags)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Open an existing file descriptor or Windows SOCKET as a UDP handle.
+  pub fn open(&mut self, sock: SockFd) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_open(self.as_mut_ptr(), sock)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Bind the UDP handle to an IP address and port.
+  /// `flags` may contain `IPV6ONLY`, `REUSEADDR` or `LINUX_RECVERR`.
+  pub fn bind(&mut self, addr: &SocketAddr, flags: UdpFlags) -> Result<(), Error> {
+    let (storage, _) = net::to_sockaddr(addr);
+
+    let rc = unsafe {
+      uv_udp_bind(self.as_mut_ptr(), &storage as *const sockaddr_storage as *const sockaddr, flags.into())
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Associate the UDP handle to a remote address and port,
+  /// so every message sent by this handle is automatically sent to that destination.
+  pub fn connect(&mut self, addr: &SocketAddr) -> Result<(), Error> {
+    let (storage, _) = net::to_sockaddr(addr);
+
+    let rc = unsafe {
+      uv_udp_connect(self.as_mut_ptr(), &storage as *const sockaddr_storage as *const sockaddr)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Disconnect the handle from its remote address.
+  pub fn disconnect(&mut self) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_connect(self.as_mut_ptr(), std::ptr::null())
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Get the remote IP and port of the UDP handle on connected UDP handles.
+  pub fn getpeername(&self) -> Result<SocketAddr, Error> {
+    let mut name = sockaddr_storage::default();
+    let mut size = std::mem::size_of::<sockaddr_storage>() as c_int;
+    let name_ptr = &mut name as *mut sockaddr_storage as *mut sockaddr;
+
+    let rc = unsafe {
+      uv_udp_getpeername(self.as_ptr(), name_ptr, &mut size)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    unsafe { net::from_sockaddr(name_ptr) }.ok_or(Error::EAFNOSUPPORT)
+  }
+
+  /// Get the local IP and port of the UDP handle.
+  pub fn getsockname(&self) -> Result<SocketAddr, Error> {
+    let mut name = sockaddr_storage::default();
+    let mut size = std::mem::size_of::<sockaddr_storage>() as c_int;
+    let name_ptr = &mut name as *mut sockaddr_storage as *mut sockaddr;
+
+    let rc = unsafe {
+      uv_udp_getsockname(self.as_ptr(), name_ptr, &mut size)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    unsafe { net::from_sockaddr(name_ptr) }.ok_or(Error::EAFNOSUPPORT)
+  }
+
+  /// Set membership for a multicast address.
+  /// When `interface_addr` is `None` the default interface is used.
+  pub fn set_membership(
+    &mut self,
+    multicast_addr: &IpAddr,
+    interface_addr: Option<&IpAddr>,
+    membership: Membership
+  ) -> Result<(), Error> {
+    let multicast_addr = ip_to_cstring(multicast_addr);
+    let interface_addr = opt_ip_to_cstring(interface_addr);
+
+    let rc = unsafe {
+      uv_udp_set_membership(
+        self.as_mut_ptr(),
+        multicast_addr.as_ptr(),
+        opt_cstr_ptr(&interface_addr),
+        membership.into()
+      )
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Set membership for a source-specific multicast group.
+  /// When `interface_addr` is `None` the default interface is used.
+  pub fn set_source_membership(
+    &mut self,
+    multicast_addr: &IpAddr,
+    interface_addr: Option<&IpAddr>,
+    source_addr: &IpAddr,
+    membership: Membership
+  ) -> Result<(), Error> {
+    let multicast_addr = ip_to_cstring(multicast_addr);
+    let interface_addr = opt_ip_to_cstring(interface_addr);
+    let source_addr = ip_to_cstring(source_addr);
+
+    let rc = unsafe {
+      uv_udp_set_source_membership(
+        self.as_mut_ptr(),
+        multicast_addr.as_ptr(),
+        opt_cstr_ptr(&interface_addr),
+        source_addr.as_ptr(),
+        membership.into()
+      )
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Set IP multicast loop flag. Makes multicast packets loop back to local sockets.
+  pub fn set_multicast_loop(&mut self, on: bool) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_set_multicast_loop(self.as_mut_ptr(), if on { 1 } else { 0 })
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Set the multicast ttl (1 through 255).
+  pub fn set_multicast_ttl(&mut self, ttl: i32) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_set_multicast_ttl(self.as_mut_ptr(), ttl as c_int)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Set the multicast interface to send or receive data on.
+  pub fn set_multicast_interface(&mut self, interface_addr: Option<&IpAddr>) -> Result<(), Error> {
+    let interface_addr = opt_ip_to_cstring(interface_addr);
+
+    let rc = unsafe {
+      uv_udp_set_multicast_interface(self.as_mut_ptr(), opt_cstr_ptr(&interface_addr))
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Set broadcast on or off.
+  pub fn set_broadcast(&mut self, on: bool) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_set_broadcast(self.as_mut_ptr(), if on { 1 } else { 0 })
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Set the time to live (1 through 255).
+  pub fn set_ttl(&mut self, ttl: i32) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_set_ttl(self.as_mut_ptr(), ttl as c_int)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Send data over the UDP socket.
+  /// `addr` must be `None` on connected handles and `Some` otherwise.
+  /// The request is freed once `send_cb` returns.
+  ///
+  /// # Safety
+  /// The memory pointed to by `bufs` must stay valid and unchanged until `send_cb` is called.
+  pub unsafe fn send(&mut self, bufs: &[Buf], addr: Option<&SocketAddr>, send_cb: UdpSendCb) -> Result<(), Error> {
+    self.send_stored(bufs, addr, Completion::Raw(send_cb), None)
+  }
+
+  /// Same as `send()`, with a closure called once the data has been sent.
+  /// See `send_owned()` for a safe version.
+  ///
+  /// # Safety
+  /// The memory pointed to by `bufs` must stay valid and unchanged until the closure is called.
+  pub unsafe fn send_with<F>(&mut self, bufs: &[Buf], addr: Option<&SocketAddr>, cb: F) -> Result<(), Error>
+  where
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    let cb: RequestFn = Box::new(cb);
+    self.send_stored(bufs, addr, Completion::from(cb), None)
+  }
+
+  /// Same as `send_with()`, but takes ownership of the datagram, which is kept alive by the
+  /// request until it completes.
+  pub fn send_owned<B, F>(&mut self, data: B, addr: Option<&SocketAddr>, cb: F) -> Result<(), Error>
+  where
+    B: Into<Vec<u8>>,
+    F: FnOnce(Result<(), Error>) + 'static
+  {
+    let data: Vec<u8> = data.into();
+    let bufs = [Buf::from(&data[..])];
+    let cb: RequestFn = Box::new(cb);
+    self.send_stored(&bufs, addr, Completion::from(cb), Some(Box::new(data)))
+  }
+
+  fn send_stored(
+    &mut self,
+    bufs: &[Buf],
+    addr: Option<&SocketAddr>,
+    completion: Completion<UdpSend>,
+    storage: Option<Box<dyn Any>>
+  ) -> Result<(), Error> {
+    // libuv asserts that there is at least one buffer.
+    if bufs.is_empty() {
+      return Err(Error::EINVAL);
+    }
+
+    let storage_addr = addr.map(net::to_sockaddr);
+    let addr_ptr = storage_addr
+      .as_ref()
+      .map_or(std::ptr::null(), |(s, _)| s as *const sockaddr_storage as *const sockaddr);
+
+    let req = OwnedReq::into_raw(completion, storage);
+
+    let rc = unsafe {
+      uv_udp_send(req, self.as_mut_ptr(), bufs.as_ptr(), bufs.len() as c_uint, addr_ptr, Some(OwnedReq::<UdpSend>::trampoline))
+    };
+
+    if rc < 0 {
+      unsafe { OwnedReq::abort(req) };
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Same as `send()`, but won't queue a send request if it can't be completed immediately.
+  /// Returns the number of bytes sent.
+  pub fn try_send(&mut self, bufs: &[Buf], addr: Option<&SocketAddr>) -> Result<usize, Error> {
+    let storage = addr.map(net::to_sockaddr);
+    let addr_ptr = storage
+      .as_ref()
+      .map_or(std::ptr::null(), |(s, _)| s as *const sockaddr_storage as *const sockaddr);
+
+    let rc = unsafe {
+      uv_udp_try_send(self.as_mut_ptr(), bufs.as_ptr(), bufs.len() as c_uint, addr_ptr)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(rc as usize)
+  }
+
+  /// Prepare for receiving data.
+  /// If the socket has not previously been bound it is bound to 0.0.0.0 and a random port.
+  pub fn recv_start(&mut self, alloc_cb: AllocCb, recv_cb: UdpRecvCb) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_recv_start(self.as_mut_ptr(), alloc_cb, recv_cb)
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Same as `recv_start()`, with a closure as receive callback.
+  ///
+  /// Datagrams are received in a buffer owned by the handle, which is reused and released
+  /// when the handle is closed. The closure receives the datagram (or the error reported by
+  /// libuv), the address of the sender and the flags of the datagram.
+  pub fn recv_start_with<F>(&mut self, cb: F) -> Result<(), Error>
+  where
+    F: FnMut(&mut Udp, Result<&[u8], Error>, Option<SocketAddr>, UdpFlags) + 'static
+  {
+    let handle = self.as_mut_handle();
+
+    if unsafe { context::get::<AllocFn>(handle.as_mut_ptr(), Slot::Alloc) }.is_none() {
+      context::set::<AllocFn>(handle, Slot::Alloc, Box::<ReadBuffer>::default());
+    }
+
+    self.recv_start(Some(stream::alloc_cb), Some(recv_cb))?;
+    context::set::<RecvFn>(self.as_mut_handle(), Slot::Callback, Box::new(cb));
+    Ok(())
+  }
+
+  /// Stop listening for incoming datagrams.
+  pub fn recv_stop(&mut self) -> Result<(), Error> {
+    let rc = unsafe {
+      uv_udp_recv_stop(self.as_mut_ptr())
+    };
+
+    if rc < 0 {
+      return Err(Error::from(rc));
+    }
+
+    Ok(())
+  }
+
+  /// Returns `true` if the handle was created with `RECVMMSG` and the platform supports it.
+  pub fn using_recvmmsg(&self) -> bool {
+    1 == unsafe {
+      uv_udp_using_recvmmsg(self.as_ptr())
+    }
+  }
+
+  /// Number of bytes queued for sending.
+  pub fn send_queue_size(&self) -> usize {
+    unsafe {
+      uv_udp_get_send_queue_size(self.as_ptr()) as usize
+    }
+  }
+
+  /// Number of send requests currently in the queue awaiting to be processed.
+  pub fn send_queue_count(&self) -> usize {
+    unsafe {
+      uv_udp_get_send_queue_count(self.as_ptr()) as usize
+    }
+  }
+}
+
+impl fmt::Debug for Udp {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("Udp")
+      // UV_HANDLE_FIELDS
+      .field("data",  &self.data)
+      .field("loop",  &self.loop_)
+      .field("type",  &self.type_)
+      .field("close_cb",  &self.close_cb)
+      .field("handle_queue",  &self.handle_queue)
+      .field("next_closing",  &self.next_closing)
+      .field("flags",  &self.flags)
+      // UV_UDP_FIELDS
+      .field("send_queue_size",  &self.send_queue_size)
+      .field("send_queue_count",  &self.send_queue_count)
+      // UV_UDP_PRIVATE_FIELDS
+      .field("alloc_cb",  &self.alloc_cb)
+      .field("recv_cb",  &self.recv_cb)
+      .field("io_watcher",  &self.io_watcher)
+      .field("write_queue",  &self.write_queue)
+      .field("write_completed_queue",  &self.write_completed_queue)
+      .finish()
+  }
+}
+
+#[test]
+fn test_layout_udp() {
+  use std::mem;
+  assert_eq!(
+    mem::size_of::<Udp>(),
+    mem::size_of::<uv_udp_t>(),
+    concat!("Size of: ", stringify!(Udp))
+  );
+  assert_eq!(
+    mem::align_of::<Udp>(),
+    mem::align_of::<uv_udp_t>(),
+    concat!("Alignment of ", stringify!(Udp))
+  );
+  assert_eq!(
+    mem::align_of::<Udp>(),
+    mem::align_of::<Handle>(),
+    concat!("Alignment of ", stringify!(Udp))
+  );
+}
+
+#[test]
+fn test_udp_init_ex_einval() {
+  use crate::handle::OwnedHandle;
+
+  let l = Loop::new().unwrap();
+
+  // The domain and the flags share the same argument.
+  for (domain, flags) in [(0x100, UdpFlags::empty()), (libc::AF_INET, UdpFlags::REUSEADDR)] {
+    let res = OwnedHandle::new(&l, |udp: &mut Udp, l| udp.init_ex(l, domain, flags));
+    assert_eq!(res.unwrap_err(), Error::EINVAL);
+  }
+}
+
+#[test]
+fn test_udp_loopback() {
+  use std::rc::Rc;
+  use std::cell::{Cell, RefCell};
+  use crate::r#loop::RunMode;
+  use crate::handle::OwnedHandle;
+
+  let l = Loop::new().unwrap();
+  let localhost = SocketAddr::from(([127, 0, 0, 1], 0));
+
+  let mut receiver = OwnedHandle::new(&l, Udp::init).unwrap();
+  receiver.get_mut().bind(&localhost, UdpFlags::empty()).unwrap();
+  let mut sender = OwnedHandle::new(&l, Udp::init).unwrap();
+  sender.get_mut().bind(&localhost, UdpFlags::empty()).unwrap();
+
+  let received = Rc::new(RefCell::new(None));
+  let datagram = received.clone();
+  receiver.get_mut().recv_start_with(move |udp, res, addr, flags| {
+    *datagram.borrow_mut() = Some((res.map(|data| data.to_vec()), addr, flags));
+    udp.recv_stop().unwrap();
+  }).unwrap();
+
+  let sent = Rc::new(Cell::new(None));
+  let status = sent.clone();
+  let to = receiver.getsockname().unwrap();
+  sender.get_mut().send_owned(&b"datagram"[..], Some(&to), move |res| status.set(Some(res))).unwrap();
+
+  l.run(RunMode::Default).unwrap();
+
+  assert_eq!(sent.get(), Some(Ok(())));
+  assert_eq!(
+    received.borrow_mut().take(),
+    Some((Ok(b"datagram".to_vec()), Some(sender.getsockname().unwrap()), UdpFlags::empty()))
+  );
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::os::raw::c_void;
+
+use crate::r#loop::Loop;
+use crate::handle::{Fd, Handle, HandleImpl, HandleType};
+use crate::bindings::{uv_handle_t, uv_walk};
+use crate::{
+  Async, Check, FsEvent, FsPoll, Idle, Pipe, Poll, Prepare, Process,
+  Signal, Tcp, Timer, Tty, Udp
+};
+
+/// Handle of a loop, downcast according to its `HandleType`.
+pub enum HandleRef<'a> {
+  Async(&'a Async),
+  Check(&'a Check),
+  FsEvent(&'a FsEvent),
+  FsPoll(&'a FsPoll),
+  Idle(&'a Idle),
+  Pipe(&'a Pipe),
+  Poll(&'a Poll),
+  Prepare(&'a Prepare),
+  Process(&'a Process),
+  Signal(&'a Signal),
+  Tcp(&'a Tcp),
+  Timer(&'a Timer),
+  Tty(&'a Tty),
+  Udp(&'a Udp),
+  /// Handle of a type without a dedicated wrapper.
+  Other(&'a Handle),
+}
+
+impl<'a> From<&'a Handle> for HandleRef<'a> {
+  fn from(handle: &'a Handle) -> Self {
+    let ptr = handle as *const Handle;
+
+    unsafe {
+      match handle.get_type() {
+        HandleType::Async     => HandleRef::Async(&*(ptr as *const Async)),
+        HandleType::Check     => HandleRef::Check(&*(ptr as *const Check)),
+        HandleType::FsEvent   => HandleRef::FsEvent(&*(ptr as *const FsEvent)),
+        HandleType::FsPoll    => HandleRef::FsPoll(&*(ptr as *const FsPoll)),
+        HandleType::Idle      => HandleRef::Idle(&*(ptr as *const Idle)),
+        HandleType::NamedPipe => HandleRef::Pipe(&*(ptr as *const Pipe)),
+        HandleType::Poll      => HandleRef::Poll(&*(ptr as *const Poll)),
+        HandleType::Prepare   => HandleRef::Prepare(&*(ptr as *const Prepare)),
+        HandleType::Process   => HandleRef::Process(&*(ptr as *const Process)),
+        HandleType::Signal    => HandleRef::Signal(&*(ptr as *const Signal)),
+        HandleType::Tcp       => HandleRef::Tcp(&*(ptr as *const Tcp)),
+        HandleType::Timer     => HandleRef::Timer(&*(ptr as *const Timer)),
+        HandleType::Tty       => HandleRef::Tty(&*(ptr as *const Tty)),
+        HandleType::Udp       => HandleRef::Udp(&*(ptr as *const Udp)),
+        _                     => HandleRef::Other(handle),
+      }
+    }
+  }
+}
+
+impl HandleRef<'_> {
+  /// The underlying handle, whatever its type.
+  pub fn as_handle(&self) -> &Handle {
+    match self {
+      HandleRef::Async(handle)   => handle.as_handle(),
+      HandleRef::Check(handle)   => handle.as_handle(),
+      HandleRef::FsEvent(handle) => handle.as_handle(),
+      HandleRef::FsPoll(handle)  => handle.as_handle(),
+      HandleRef::Idle(handle)    => handle.as_handle(),
+      HandleRef::Pipe(handle)    => handle.as_handle(),
+      HandleRef::Poll(handle)    => handle.as_handle(),
+      HandleRef::Prepare(handle) => handle.as_handle(),
+      HandleRef::Process(handle) => handle.as_handle(),
+      HandleRef::Signal(handle)  => handle.as_handle(),
+      HandleRef::Tcp(handle)     => handle.as_handle(),
+      HandleRef::Timer(handle)   => handle.as_handle(),
+      HandleRef::Tty(handle)     => handle.as_handle(),
+      HandleRef::Udp(handle)     => handle.as_handle(),
+      HandleRef::Other(handle)   => handle,
+    }
+  }
+}
+
+impl fmt::Debug for HandleRef<'_> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_tuple("HandleRef")
+      .field(&self.as_handle().get_type())
+      .field(&self.as_handle().as_ptr())
+      .finish()
+  }
+}
+
+/// State of a handle at the time of `Loop::handles()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandleInfo {
+  pub r#type:  HandleType,
+  pub active:  bool,
+  pub has_ref: bool,
+  pub closing: bool,
+  /// Platform dependent file descriptor, for the handle types that have one.
+  pub fd:      Option<Fd>,
+}
+
+impl From<&Handle> for HandleInfo {
+  fn from(handle: &Handle) -> Self {
+    HandleInfo {
+      r#type:  handle.get_type(),
+      active:  handle.is_active(),
+      has_ref: handle.has_ref(),
+      closing: handle.is_closing(),
+      fd:      handle.fileno().ok(),
+    }
+  }
+}
+
+unsafe extern "C" fn walk_cb<F>(handle: *mut uv_handle_t, arg: *mut c_void)
+where
+  F: FnMut(HandleRef<'_>)
+{
+  let cb = &mut *(arg as *mut F);
+  cb(HandleRef::from(&*(handle as *const Handle)))
+}
+
+impl Loop {
+  /// Call `cb` with every handle of the loop, including the ones being closed.
+  pub fn walk<F>(&self, mut cb: F)
+  where
+    F: FnMut(HandleRef<'_>)
+  {
+    unsafe {
+      uv_walk(self.as_raw(), Some(walk_cb::<F>), &mut cb as *mut F as *mut c_void)
+    }
+  }
+
+  /// Snapshot of the handles of the loop, e.g. to find the ones still open at shutdown.
+  pub fn handles(&self) -> Vec<HandleInfo> {
+    let mut handles = Vec::new();
+    self.walk(|handle| handles.push(HandleInfo::from(handle.as_handle())));
+    handles
+  }
+}
+
+#[test]
+fn test_loop_handles() {
+  use crate::r#loop::RunMode;
+  use crate::handle::OwnedHandle;
+
+  let l = Loop::new().unwrap();
+  let mut timer = OwnedHandle::new(&l, Timer::init).unwrap();
+  let tcp = OwnedHandle::new(&l, Tcp::init).unwrap();
+
+  timer.get_mut().start_with(60_000, 0, |_| {}).unwrap();
+  tcp.close();
+
+  let handles = l.handles();
+  assert_eq!(handles.len(), 2);
+
+  let timer_info = handles.iter().find(|info| info.r#type == HandleType::Timer).unwrap();
+  assert!(timer_info.active && timer_info.has_ref && !timer_info.closing);
+  assert_eq!(timer_info.fd, None);
+
+  let tcp_info = handles.iter().find(|info| info.r#type == HandleType::Tcp).unwrap();
+  assert!(!tcp_info.active && tcp_info.closing);
+
+  let mut timers = 0;
+  l.walk(|handle| if let HandleRef::Timer(handle) = handle {
+    assert!(handle.is_active());
+    timers += 1;
+  });
+  assert_eq!(timers, 1);
+
+  drop(timer);
+  l.run(RunMode::Default).unwrap();
+  assert!(l.handles().is_empty());
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+use std::cell::Cell;
+use std::thread;
+use std::panic::{self, AssertUnwindSafe};
+use std::os::raw::{c_int, c_void};
+
+use crate::r#loop::{self, Loop};
+use crate::error::Error;
+use crate::req::{Work, ReqImpl};
+use crate::bindings::{uv_work_t, uv_queue_work};
+
+/// State of a queued work item, stored in the request `data` slot.
+struct WorkData<W, T, A> {
+  work:   Option<W>,
+  result: Option<thread::Result<T>>,
+  after:  A,
+  req:    Rc<Cell<*mut uv_work_t>>,
+}
+
+/// Pending `queue_work()` item, which can be used to cancel it.
+pub struct QueuedWork {
+  req: Rc<Cell<*mut uv_work_t>>,
+}
+
+impl QueuedWork {
+  /// Cancel the work item if it has not started yet. Its after-callback is then called with
+  /// `ECANCELED`. Fails with `EBUSY` if the work is executing or has finished executing.
+  pub fn cancel(&self) -> Result<(), Error> {
+    let req = self.req.get();
+
+    if req.is_null() {
+      return Err(Error::EBUSY);
+    }
+
+    unsafe { (*(req as *mut Work)).cancel() }
+  }
+
+  /// Returns `true` once the after-callback ran.
+  pub fn is_done(&self) -> bool {
+    self.req.get().is_null()
+  }
+}
+
+impl fmt::Debug for QueuedWork {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("QueuedWork")
+      .field("req", &self.req.get())
+      .finish()
+  }
+}
+
+unsafe extern "C" fn work_cb<W, T, A>(req: *mut uv_work_t)
+where
+  W: FnOnce() -> T + Send
+{
+  let data = &mut *((*req).data as *mut WorkData<W, T, A>);
+
+  if let Some(work) = data.work.take() {
+    data.result = Some(panic::catch_unwind(AssertUnwindSafe(work)));
+  }
+}
+
+unsafe extern "C" fn after_work_cb<W, T, A>(req: *mut uv_work_t, status: c_int)
+where
+  A: FnOnce(Result<T, Error>)
+{
+  let req = Box::from_raw(req as *mut Work);
+  let data = Box::from_raw(req.data as *mut WorkData<W, T, A>);
+  data.req.set(ptr::null_mut());
+
+  let WorkData { result, after, .. } = *data;
+
+  match result {
+    _ if status < 0       => after(Err(Error::from(status))),
+    Some(Ok(value))       => after(Ok(value)),
+    Some(Err(payload))    => r#loop::set_panic(req.loop_, payload),
+    None                  => after(Err(Error::ECANCELED)),
+  }
+}
+
+impl Loop {
+  /// Run `work` on the threadpool, then `after` with its result on the loop thread.
+  ///
+  /// If the work is cancelled through the returned `QueuedWork`, `after` receives `ECANCELED`.
+  /// If `work` panics, `after` is not called: the loop is stopped and the panic is resumed
+  /// from `Loop::run`.
+  pub fn queue_work<W, T, A>(&self, work: W, after: A) -> Result<QueuedWork, Error>
+  where
+    W: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+    A: FnOnce(Result<T, Error>) + 'static
+  {
+    let token = Rc::new(Cell::new(ptr::null_mut()));
+    let req = Box::into_raw(Work::boxed());
+
+    let data: Box<WorkData<W, T, A>> = Box::new(WorkData {
+      work:   Some(work),
+      result: None,
+      after,
+      req:    token.clone(),
+    });
+
+    unsafe {
+      (*req).data = Box::into_raw(data) as *mut c_void;
+    }
+
+    let rc = unsafe {
+      uv_queue_work(self.as_raw(), req, Some(work_cb::<W, T, A>), Some(after_work_cb::<W, T, A>))
+    };
+
+    if rc < 0 {
+      unsafe {
+        drop(Box::from_raw((*req).data as *mut WorkData<W, T, A>));
+        drop(Box::from_raw(req));
+      }
+      return Err(Error::from(rc));
+    }
+
+    token.set(req);
+    Ok(QueuedWork { req: token })
+  }
+}
+
+#[test]
+fn test_queue_work_result() {
+  use crate::r#loop::RunMode;
+
+  let l = Loop::new().unwrap();
+  let result = Rc::new(Cell::new(None));
+  let res = result.clone();
+
+  let work = l.queue_work(|| 6 * 7, move |value| res.set(Some(value))).unwrap();
+  assert!(!work.is_done());
+
+  l.run(RunMode::Default).unwrap();
+  assert_eq!(result.get(), Some(Ok(42)));
+  assert!(work.is_done());
+}
+
+#[test]
+fn test_queue_work_cancel() {
+  use std::sync::{Arc, Mutex, Condvar};
+  use crate::r#loop::RunMode;
+
+  let l = Loop::new().unwrap();
+  let threads = std::env::var("UV_THREADPOOL_SIZE").ok().and_then(|n| n.parse().ok()).unwrap_or(4);
+
+  // Keep every threadpool thread busy so that the last item stays queued.
+  let gate = Arc::new((Mutex::new(false), Condvar::new()));
+  for _ in 0..threads {
+    let gate = gate.clone();
+    l.queue_work(move || {
+      let (open, cond) = &*gate;
+      let _open = cond.wait_while(open.lock().unwrap(), |open| !*open).unwrap();
+    }, |res| res.unwrap()).unwrap();
+  }
+
+  let result = Rc::new(Cell::new(None));
+  let res = result.clone();
+  let work = l.queue_work(|| 1, move |value| res.set(Some(value))).unwrap();
+  work.cancel().unwrap();
+
+  let (open, cond) = &*gate;
+  *open.lock().unwrap() = true;
+  cond.notify_all();
+
+  l.run(RunMode::Default).unwrap();
+  assert_eq!(result.get(), Some(Err(Error::ECANCELED)));
+  assert!(work.is_done());
+  assert_eq!(work.cancel(), Err(Error::EBUSY));
+}
+
+#[test]
+fn test_queue_work_panic() {
+  use crate::r#loop::RunMode;
+
+  let l = Loop::new().unwrap();
+  let called = Rc::new(Cell::new(false));
+  let after = called.clone();
+
+  l.queue_work(|| -> u32 { panic!("work panicked") }, move |_| after.set(true)).unwrap();
+
+  let payload = panic::catch_unwind(AssertUnwindSafe(|| l.run(RunMode::Default))).unwrap_err();
+  assert_eq!(payload.downcast_ref::<&str>(), Some(&"work panicked"));
+  assert!(!called.get());
+  l.run(RunMode::Default).unwrap();
+}